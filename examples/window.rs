@@ -0,0 +1,25 @@
+use vsb::*;
+
+fn main() {
+    unsafe {
+        let app = App::new(WindowOptions::new("vsb").with_size(640, 480)).unwrap();
+        let gl = app.gl().clone();
+        let shaders = ShaderLibrary::new(&gl).unwrap();
+        let circle = Circle::new(gl.clone(), 40.).unwrap();
+        set_clear_color(&gl, ColorUniforms::new(0.1, 0.1, 0.1));
+
+        app.on_exit(|leaks| {
+            if !leaks.is_empty() {
+                eprintln!("{}", leaks);
+            }
+        }).run(cgmath::Vector2::new(0., 0.), |position, frame| {
+            let (width, height) = frame.resolution;
+            position.x = width as f32 / 2. + frame.elapsed.cos() * 100.;
+            position.y = height as f32 / 2. + frame.elapsed.sin() * 100.;
+        }, move |position, frame| {
+            use glow::HasContext;
+            frame.gl.clear(glow::COLOR_BUFFER_BIT);
+            circle.draw_with(&shaders.solid, *position, ColorUniforms::new(1., 0.5, 0.), frame.resolution);
+        });
+    }
+}
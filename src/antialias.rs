@@ -0,0 +1,241 @@
+use glow::*;
+use std::sync::Arc;
+
+use lyon::math::Point;
+use lyon::path::iterator::PathIterator;
+use lyon::path::{FillRule, Path, PathEvent};
+use lyon::tessellation::{BuffersBuilder, LineJoin, Side, StrokeOptions, StrokeTessellator, StrokeVertex, TessellationError, VertexBuffers};
+
+use crate::mesh::{check_tolerance, float_bytes, index_bytes, VertexArray};
+use crate::ATTRIBUTE_COVERAGE;
+use crate::ATTRIBUTE_POSITION;
+
+/// How a filled shape's edges are smoothed. Multisampling isn't chosen per shape, it comes
+/// from the framebuffer: `RenderTarget::new_multisampled` or `WindowOptions::with_samples`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Antialiasing {
+    #[default]
+    None,
+    /// A strip of `width` local units around the outside of the shape that fades its alpha from
+    /// 1 to 0. It's built at tessellation time, so it scales with the shape: use about one pixel
+    /// divided by the scale it's drawn at. Works on any context, with any of the bundled programs
+    /// except `batch`, `instanced` and `text`. Programs of your own get it in the `coverage`
+    /// attribute, and a `premultiplied` uniform telling whether to scale the whole color by it.
+    Fringe {
+        width: f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FringeVertex {
+    pub position: Point,
+    // alpha multiplier, 1 on the shape's edge and 0 on the outer edge of the fringe
+    pub coverage: f32
+}
+
+pub type FringeGeometry = VertexBuffers<FringeVertex, u16>;
+
+/// Tessellates the fringe of `path` filled with `fill_rule`, to draw right after the fill.
+pub fn fringe (path: &Path, width: f32, tolerance: f32, fill_rule: FillRule) -> Result<FringeGeometry, TessellationError> {
+    check_tolerance(tolerance)?;
+    let mut geometry: FringeGeometry = VertexBuffers::new();
+    let options = StrokeOptions::tolerance(tolerance).with_line_width(width).with_line_join(LineJoin::Miter);
+    let mut tessellator = StrokeTessellator::new();
+
+    // the fringe goes on the side of each sub-path that isn't filled, which depends on its direction
+    // and on the sub-paths around it, so they're stroked one at a time
+    let sub_paths = sub_paths(path);
+    let outlines: Vec<Vec<Point>> = sub_paths.iter().map(|sub_path| outline(sub_path, tolerance)).collect();
+    for (index, sub_path) in sub_paths.into_iter().enumerate() {
+        let left_is_empty = match edge(&outlines, index, fill_rule) {
+            Some(left_is_empty) => left_is_empty,
+            None => continue
+        };
+        tessellator.tessellate(sub_path, &options, &mut BuffersBuilder::new(&mut geometry, |vertex: StrokeVertex| {
+            // the stroke is centered on the path, the fringe has to start on it and only grow outwards
+            if (vertex.side() == Side::Left) == left_is_empty {
+                FringeVertex {
+                    position: vertex.position_on_path() + vertex.normal() * width,
+                    coverage: 0.
+                }
+            } else {
+                FringeVertex {
+                    position: vertex.position_on_path(),
+                    coverage: 1.
+                }
+            }
+        }))?;
+    }
+    Ok(geometry)
+}
+
+fn sub_paths (path: &Path) -> Vec<Vec<PathEvent>> {
+    let mut sub_paths = Vec::new();
+    for event in path.iter() {
+        if let PathEvent::Begin { .. } = event {
+            sub_paths.push(Vec::new());
+        }
+        if let Some(sub_path) = sub_paths.last_mut() {
+            sub_path.push(event);
+        }
+    }
+    sub_paths
+}
+
+// the sub-path flattened to a polygon
+fn outline (sub_path: &[PathEvent], tolerance: f32) -> Vec<Point> {
+    sub_path.iter().copied().flattened(tolerance).filter_map(|event| match event {
+        PathEvent::Begin { at } => Some(at),
+        PathEvent::Line { to, .. } => Some(to),
+        _ => None
+    }).collect()
+}
+
+// lyon's left is the side its normals point to, which is the outside of a polygon with a negative area
+fn area (polygon: &[Point]) -> f32 {
+    let mut area = 0.;
+    for (index, from) in polygon.iter().enumerate() {
+        let to = polygon[(index + 1) % polygon.len()];
+        area += from.x * to.y - to.x * from.y;
+    }
+    area / 2.
+}
+
+// whether lyon's left of the sub-path is the side that isn't filled, None when both sides are
+// filled or both empty and there's no edge to smooth
+fn edge (outlines: &[Vec<Point>], index: usize, fill_rule: FillRule) -> Option<bool> {
+    let polygon = &outlines[index];
+    let point = *polygon.first()?;
+    let area = area(polygon);
+    if area == 0. {
+        return None;
+    }
+
+    // the winding number just outside the sub-path comes from the others, inside it adds its own
+    let outside: i32 = outlines.iter().enumerate()
+        .filter(|(other, _)| *other != index)
+        .map(|(_, other)| winding(other, point))
+        .sum();
+    let inside = outside + if area > 0. { 1 } else { -1 };
+    let filled = |winding: i32| match fill_rule {
+        FillRule::EvenOdd => winding % 2 != 0,
+        FillRule::NonZero => winding != 0
+    };
+    match (filled(inside), filled(outside)) {
+        (true, false) => Some(area < 0.),
+        (false, true) => Some(area > 0.),
+        _ => None
+    }
+}
+
+// how many times the polygon winds around the point, counterclockwise being positive like in `area`
+fn winding (polygon: &[Point], point: Point) -> i32 {
+    let mut winding = 0;
+    for (index, from) in polygon.iter().enumerate() {
+        let to = polygon[(index + 1) % polygon.len()];
+        // which side of the edge the point is on, positive to the left
+        let side = (to.x - from.x) * (point.y - from.y) - (point.x - from.x) * (to.y - from.y);
+        if from.y <= point.y && to.y > point.y && side > 0. {
+            winding += 1;
+        } else if from.y > point.y && to.y <= point.y && side < 0. {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+/// The fringe of a `Mesh` on the GPU, with its own vertex array since it carries a coverage attribute.
+#[derive(Debug)]
+pub(crate) struct Fringe {
+    vertex_array: VertexArray,
+    geometry: FringeGeometry,
+    gl: Arc<Context>
+}
+
+impl Fringe {
+    pub(crate) unsafe fn new (gl: Arc<Context>, geometry: FringeGeometry) -> Self {
+        let vertex_array = VertexArray::new(&gl, &[(ATTRIBUTE_POSITION, 2), (ATTRIBUTE_COVERAGE, 1)]);
+        let vertex_buffer_data = float_bytes(geometry.vertices.iter().flat_map(|vertex| [vertex.position.x, vertex.position.y, vertex.coverage]));
+        gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &vertex_buffer_data, glow::STATIC_DRAW);
+        gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, &index_bytes(&geometry.indices), glow::STATIC_DRAW);
+
+        Self {
+            vertex_array,
+            geometry,
+            gl
+        }
+    }
+
+    pub(crate) fn geometry (&self) -> &FringeGeometry {
+        &self.geometry
+    }
+
+    // expects the program and its uniforms to be set up already, by the fill that came before
+    pub(crate) unsafe fn draw (&self) {
+        self.vertex_array.bind(&self.gl);
+        self.gl.draw_elements(glow::TRIANGLES, self.geometry.indices.len() as i32, glow::UNSIGNED_SHORT, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon::math::point;
+    use lyon::path::builder::PathBuilder;
+    use lyon::path::Winding;
+
+    fn circles (circles: &[(f32, Winding)]) -> Path {
+        let mut builder = Path::builder();
+        for (radius, winding) in circles.iter() {
+            builder.add_circle(point(0., 0.), *radius, *winding);
+        }
+        builder.build()
+    }
+
+    // the distances from the center of the edge and of the outer edge of the fringe, each rounded
+    // to the nearest unit and in ascending order
+    fn radii (geometry: &FringeGeometry, coverage: f32) -> Vec<i32> {
+        let mut radii: Vec<i32> = geometry.vertices.iter()
+            .filter(|vertex| vertex.coverage == coverage)
+            .map(|vertex| vertex.position.to_vector().length().round() as i32)
+            .collect();
+        radii.sort_unstable();
+        radii.dedup();
+        radii
+    }
+
+    #[test]
+    fn fringe_grows_out_of_a_circle () {
+        let geometry = fringe(&circles(&[(10., Winding::Positive)]), 2., 0.01, FillRule::EvenOdd).unwrap();
+        assert_eq!(radii(&geometry, 1.), vec![10]);
+        assert_eq!(radii(&geometry, 0.), vec![12]);
+    }
+
+    #[test]
+    fn fringe_grows_out_of_a_reversed_circle () {
+        let geometry = fringe(&circles(&[(10., Winding::Negative)]), 2., 0.01, FillRule::NonZero).unwrap();
+        assert_eq!(radii(&geometry, 1.), vec![10]);
+        assert_eq!(radii(&geometry, 0.), vec![12]);
+    }
+
+    #[test]
+    fn fringe_grows_into_an_even_odd_hole () {
+        let geometry = fringe(&circles(&[(20., Winding::Positive), (10., Winding::Positive)]), 2., 0.01, FillRule::EvenOdd).unwrap();
+        assert_eq!(radii(&geometry, 1.), vec![10, 20]);
+        assert_eq!(radii(&geometry, 0.), vec![8, 22]);
+    }
+
+    #[test]
+    fn non_zero_inner_sub_path_has_no_fringe () {
+        let geometry = fringe(&circles(&[(20., Winding::Positive), (10., Winding::Positive)]), 2., 0.01, FillRule::NonZero).unwrap();
+        assert_eq!(radii(&geometry, 1.), vec![20]);
+        assert_eq!(radii(&geometry, 0.), vec![22]);
+    }
+
+    #[test]
+    fn non_zero_hole_winds_the_other_way () {
+        let geometry = fringe(&circles(&[(20., Winding::Positive), (10., Winding::Negative)]), 2., 0.01, FillRule::NonZero).unwrap();
+        assert_eq!(radii(&geometry, 1.), vec![10, 20]);
+        assert_eq!(radii(&geometry, 0.), vec![8, 22]);
+    }
+}
@@ -0,0 +1,244 @@
+//! A window with a GL context and a frame loop, so apps don't have to repeat the glutin setup.
+
+use glow::*;
+use std::cell::Cell;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use glutin::dpi::LogicalSize;
+use glutin::event::{Event, WindowEvent};
+use glutin::event_loop::{ControlFlow, EventLoop};
+use glutin::window::{Window, WindowBuilder};
+use glutin::{ContextBuilder, ContextWrapper, PossiblyCurrent};
+
+use crate::{LeakReport, ResourceRegistry};
+
+#[derive(Debug)]
+pub enum AppError {
+    Creation(glutin::CreationError),
+    Context(glutin::ContextError)
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Creation(error) => write!(f, "cannot create the window: {}", error),
+            AppError::Context(error) => write!(f, "cannot make the GL context current: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+#[derive(Debug, Clone)]
+pub struct WindowOptions {
+    pub title: String,
+    // logical size, the framebuffer is scaled by the monitor's DPI factor
+    pub width: u32,
+    pub height: u32,
+    pub resizable: bool,
+    pub vsync: bool,
+    pub samples: u16
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            title: String::from("vsb"),
+            width: 800,
+            height: 600,
+            resizable: true,
+            vsync: true,
+            samples: 0
+        }
+    }
+}
+
+impl WindowOptions {
+    pub fn new (title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_size (mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_resizable (mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn with_vsync (mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    /// MSAA samples for the default framebuffer, 0 disables multisampling.
+    pub fn with_samples (mut self, samples: u16) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+/// What the `update` and `draw` callbacks get each frame.
+pub struct Frame<'a> {
+    pub gl: &'a Arc<Context>,
+    pub window: &'a Window,
+    // framebuffer size in physical pixels, to pass as the resolution of `draw_with`
+    pub resolution: (u32, u32),
+    // seconds since the previous frame and since the loop started
+    pub delta: f32,
+    pub elapsed: f32,
+    // window events received since the previous frame
+    pub events: &'a [WindowEvent<'static>],
+    exit: &'a Cell<bool>
+}
+
+impl<'a> Frame<'a> {
+    /// Closes the window after this frame.
+    pub fn exit (&self) {
+        self.exit.set(true);
+    }
+}
+
+pub struct App {
+    event_loop: EventLoop<()>,
+    context: ContextWrapper<PossiblyCurrent, Window>,
+    gl: Arc<Context>,
+    on_exit: Option<Box<dyn FnOnce(LeakReport)>>
+}
+
+impl App {
+    /// Opens the window and makes its context current, so shaders and shapes can be created
+    /// with `gl` before calling `run`.
+    pub unsafe fn new (options: WindowOptions) -> Result<Self, AppError> {
+        let event_loop = EventLoop::new();
+        let window = WindowBuilder::new()
+            .with_title(options.title)
+            .with_inner_size(LogicalSize::new(options.width, options.height))
+            .with_resizable(options.resizable);
+
+        let context = ContextBuilder::new()
+            .with_vsync(options.vsync)
+            .with_multisampling(options.samples)
+            .with_stencil_buffer(8)
+            .build_windowed(window, &event_loop)
+            .map_err(AppError::Creation)?;
+        let context = context.make_current().map_err(|(_, error)| AppError::Context(error))?;
+
+        let gl = Arc::new(Context::from_loader_function(|name| context.get_proc_address(name) as *const _));
+        let size = context.window().inner_size();
+        gl.viewport(0, 0, size.width as i32, size.height as i32);
+
+        Ok(Self {
+            event_loop,
+            context,
+            gl,
+            on_exit: None
+        })
+    }
+
+    pub fn gl (&self) -> &Arc<Context> {
+        &self.gl
+    }
+
+    pub fn window (&self) -> &Window {
+        self.context.window()
+    }
+
+    pub fn resolution (&self) -> (u32, u32) {
+        self.window().inner_size().into()
+    }
+
+    /// Called once the loop has ended and the state and closures passed to `run` are dropped,
+    /// with the GL objects that are still alive, which is anything kept out of them.
+    pub fn on_exit<F: FnOnce(LeakReport) + 'static> (mut self, on_exit: F) -> Self {
+        self.on_exit = Some(Box::new(on_exit));
+        self
+    }
+
+    /// Runs the frame loop until the window is closed or `Frame::exit` is called, calling `update`
+    /// then `draw` on `state` once per frame and swapping buffers after. Resizes keep the viewport
+    /// and `Frame::resolution` matched to the window. Never returns.
+    pub fn run<S, U, D> (self, state: S, update: U, draw: D) -> !
+    where
+        S: 'static,
+        U: FnMut(&mut S, &Frame) + 'static,
+        D: FnMut(&S, &Frame) + 'static
+    {
+        let Self { event_loop, context, gl, mut on_exit } = self;
+        let registry = ResourceRegistry::of(&gl);
+        // winit never drops the loop's closure, so everything holding GL objects is dropped by hand on exit
+        let mut running = Some((state, update, draw));
+        let mut resolution: (u32, u32) = context.window().inner_size().into();
+        let mut events = Vec::new();
+        let exit = Cell::new(false);
+        let start = Instant::now();
+        let mut last_frame = start;
+
+        event_loop.run(move |event, _, control_flow| {
+            *control_flow = ControlFlow::Poll;
+
+            match event {
+                Event::WindowEvent { event, .. } => {
+                    match &event {
+                        WindowEvent::Resized(size) => {
+                            context.resize(*size);
+                            resolution = (*size).into();
+                            unsafe {
+                                gl.viewport(0, 0, size.width as i32, size.height as i32);
+                            }
+                        },
+                        WindowEvent::CloseRequested => exit.set(true),
+                        _ => {}
+                    }
+                    if let Some(event) = event.to_static() {
+                        events.push(event);
+                    }
+                },
+                Event::MainEventsCleared => context.window().request_redraw(),
+                Event::RedrawRequested(_) => {
+                    let now = Instant::now();
+                    let frame = Frame {
+                        gl: &gl,
+                        window: context.window(),
+                        resolution,
+                        delta: (now - last_frame).as_secs_f32(),
+                        elapsed: (now - start).as_secs_f32(),
+                        events: &events,
+                        exit: &exit
+                    };
+                    last_frame = now;
+
+                    if let Some((state, update, draw)) = running.as_mut() {
+                        update(state, &frame);
+                        draw(state, &frame);
+                    }
+                    context.swap_buffers().unwrap();
+                    unsafe {
+                        registry.collect(&gl);
+                    }
+                    events.clear();
+                },
+                Event::LoopDestroyed => {
+                    running.take();
+                    let leaks = unsafe { ResourceRegistry::destroy(&gl) };
+                    if let Some(on_exit) = on_exit.take() {
+                        on_exit(leaks);
+                    }
+                },
+                _ => {}
+            }
+
+            if exit.get() {
+                *control_flow = ControlFlow::Exit;
+            }
+        })
+    }
+}
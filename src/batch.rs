@@ -0,0 +1,109 @@
+use glow::*;
+use std::sync::Arc;
+
+use cgmath::{Matrix4, Vector4};
+
+use crate::mesh::{float_bytes, index_bytes, write_buffer, VertexArray};
+use crate::{BlendMode, ColorUniforms, Mesh, Program, ProjectionUniforms, Shape, Transform2D, TransformUniforms, Uniforms, ATTRIBUTE_COLOR, ATTRIBUTE_POSITION};
+
+const FLOATS_PER_VERTEX: usize = 6; // x, y, r, g, b, a
+// indices are u16 for ES 2.0 and WebGL1, 0xffff is left out since ES 3.0 can use it for primitive restart
+const MAX_VERTICES_PER_DRAW: usize = u16::MAX as usize;
+
+/// Collects shapes into one shared vertex/index buffer and draws them all with a single
+/// `draw_elements`. Vertices are transformed on the CPU and carry their own color, so it's
+/// meant for the `batch` program of the `ShaderLibrary`, or any program with `position` and `color` attributes.
+pub struct Batch {
+    vertex_array: VertexArray,
+    vertices: Vec<f32>,
+    indices: Vec<u16>,
+    // where each draw call starts, as (first vertex, first index), once there are too many vertices for one
+    draws: Vec<(usize, usize)>,
+    // sizes in bytes of what's currently allocated on the GPU
+    vertex_capacity: usize,
+    index_capacity: usize,
+    pub blend_mode: BlendMode,
+    gl: Arc<Context>
+}
+
+impl Batch {
+    pub unsafe fn new (gl: Arc<Context>) -> Self {
+        Self {
+            vertex_array: VertexArray::new(&gl, &[(ATTRIBUTE_POSITION, 2), (ATTRIBUTE_COLOR, 4)]),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            draws: Vec::new(),
+            vertex_capacity: 0,
+            index_capacity: 0,
+            blend_mode: BlendMode::Normal,
+            gl
+        }
+    }
+
+    /// Queues a shape, the batched equivalent of `draw_with`.
+    pub fn add<S: Shape + ?Sized> (&mut self, shape: &S, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>) {
+        self.add_mesh(shape.mesh(), &TransformUniforms::from(transform.into()), color);
+    }
+
+    pub fn add_mesh (&mut self, mesh: &Mesh, transform: &TransformUniforms, color: impl Into<ColorUniforms>) {
+        let color = color.into();
+        let geometry = mesh.geometry();
+        let matrix: Matrix4<f32> = transform.transform;
+        let first_vertex = self.draws.last().map_or(0, |(first_vertex, _)| *first_vertex);
+        if self.draws.is_empty() || self.len() - first_vertex + geometry.vertices.len() > MAX_VERTICES_PER_DRAW {
+            self.draws.push((self.len(), self.indices.len()));
+        }
+        let base = (self.len() - self.draws.last().unwrap().0) as u16;
+
+        self.vertices.reserve(geometry.vertices.len() * FLOATS_PER_VERTEX);
+        for vertex in geometry.vertices.iter() {
+            let position = matrix * Vector4::new(vertex.x, vertex.y, 0., 1.);
+            self.vertices.extend_from_slice(&[position.x, position.y]);
+            self.vertices.extend_from_slice(&color.color);
+        }
+        self.indices.extend(geometry.indices.iter().map(|index| base + *index));
+    }
+
+    pub fn len (&self) -> usize {
+        self.vertices.len() / FLOATS_PER_VERTEX
+    }
+
+    pub fn is_empty (&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn clear (&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.draws.clear();
+    }
+
+    /// Draws everything queued since the last flush and empties the batch. That's one draw call
+    /// for every 65535 vertices.
+    pub fn flush (&mut self, program: &Program, projection: impl Into<ProjectionUniforms>) {
+        if self.is_empty() {
+            return;
+        }
+
+        unsafe {
+            program.bind();
+            self.blend_mode.apply(&self.gl);
+            self.vertex_array.bind(&self.gl);
+            projection.into().set_uniforms(program);
+
+            for (draw, (first_vertex, first_index)) in self.draws.iter().enumerate() {
+                let (end_vertex, end_index) = self.draws.get(draw + 1).copied().unwrap_or((self.len(), self.indices.len()));
+
+                let vertex_buffer_data = float_bytes(self.vertices[first_vertex * FLOATS_PER_VERTEX..end_vertex * FLOATS_PER_VERTEX].iter().copied());
+                let index_buffer_data = index_bytes(&self.indices[*first_index..end_index]);
+
+                self.vertex_capacity = write_buffer(&self.gl, ARRAY_BUFFER, &vertex_buffer_data, self.vertex_capacity, glow::STREAM_DRAW);
+                self.index_capacity = write_buffer(&self.gl, ELEMENT_ARRAY_BUFFER, &index_buffer_data, self.index_capacity, glow::STREAM_DRAW);
+
+                self.gl.draw_elements(TRIANGLES, (end_index - first_index) as i32, UNSIGNED_SHORT, 0);
+            }
+        }
+
+        self.clear();
+    }
+}
@@ -0,0 +1,122 @@
+use cgmath::{Matrix4, SquareMatrix, Vector2, Vector4};
+
+use crate::ProjectionUniforms;
+
+/// A view onto a 2D world in the same y-down pixel units as `ProjectionUniforms`. `position` is
+/// the world point shown at the center of the viewport, so `Camera2D::new(size)` matches
+/// `ProjectionUniforms::new(size)` until it's moved. Pass `&camera` wherever a draw call takes a
+/// resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    pub position: Vector2<f32>,
+    pub zoom: f32,
+    // radians, the world turns the opposite way on screen
+    pub rotation: f32,
+    pub viewport: (u32, u32)
+}
+
+impl Camera2D {
+    pub fn new (viewport: (u32, u32)) -> Self {
+        Self {
+            position: Vector2::new(viewport.0 as f32 / 2., viewport.1 as f32 / 2.),
+            zoom: 1.,
+            rotation: 0.,
+            viewport
+        }
+    }
+
+    /// Keeps the world point at the center of the viewport where it is.
+    pub fn set_viewport (&mut self, viewport: (u32, u32)) {
+        self.viewport = viewport;
+    }
+
+    /// Moves the camera by a distance in world units.
+    pub fn pan (&mut self, x: f32, y: f32) {
+        self.position += Vector2::new(x, y);
+    }
+
+    /// Moves the camera by a distance in screen pixels, as when dragging with the mouse. Does
+    /// nothing at zoom 0.
+    pub fn pan_screen (&mut self, x: f32, y: f32) {
+        if let (Some(origin), Some(moved)) = (self.screen_to_world(Vector2::new(0., 0.)), self.screen_to_world(Vector2::new(x, y))) {
+            self.position -= moved - origin;
+        }
+    }
+
+    pub fn rotate (&mut self, angle: f32) {
+        self.rotation += angle;
+    }
+
+    /// Multiplies the zoom by `factor` keeping the world point under `screen` in place, as when
+    /// zooming towards the mouse cursor.
+    pub fn zoom_at (&mut self, screen: Vector2<f32>, factor: f32) {
+        let before = self.screen_to_world(screen);
+        self.zoom *= factor;
+        if let (Some(before), Some(after)) = (before, self.screen_to_world(screen)) {
+            self.position += before - after;
+        }
+    }
+
+    /// World to screen pixels.
+    pub fn view_matrix (&self) -> Matrix4<f32> {
+        Matrix4::from_translation(cgmath::vec3(self.viewport.0 as f32 / 2., self.viewport.1 as f32 / 2., 0.))
+            * Matrix4::from_angle_z(cgmath::Rad(-self.rotation))
+            * Matrix4::from_scale(self.zoom)
+            * Matrix4::from_translation(cgmath::vec3(-self.position.x, -self.position.y, 0.))
+    }
+
+    /// World to clip space, what ends up in the `projection` uniform.
+    pub fn matrix (&self) -> Matrix4<f32> {
+        ProjectionUniforms::new(self.viewport).projection * self.view_matrix()
+    }
+
+    pub fn world_to_screen (&self, world: Vector2<f32>) -> Vector2<f32> {
+        let screen = self.view_matrix() * Vector4::new(world.x, world.y, 0., 1.);
+        Vector2::new(screen.x, screen.y)
+    }
+
+    /// `None` at zoom 0, when the whole world is squashed onto the center of the viewport.
+    pub fn screen_to_world (&self, screen: Vector2<f32>) -> Option<Vector2<f32>> {
+        let inverse = self.view_matrix().invert()?;
+        let world = inverse * Vector4::new(screen.x, screen.y, 0., 1.);
+        Some(Vector2::new(world.x, world.y))
+    }
+}
+
+impl From<&Camera2D> for ProjectionUniforms {
+    fn from(camera: &Camera2D) -> Self {
+        ProjectionUniforms::from_matrix(camera.matrix()).with_viewport(camera.viewport)
+    }
+}
+
+impl From<Camera2D> for ProjectionUniforms {
+    fn from(camera: Camera2D) -> Self {
+        ProjectionUniforms::from(&camera)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn screen_to_world_inverts_world_to_screen () {
+        let mut camera = Camera2D::new((800, 600));
+        camera.pan(30., -20.);
+        camera.rotate(0.5);
+        camera.zoom_at(Vector2::new(100., 100.), 2.);
+        let world = camera.screen_to_world(camera.world_to_screen(Vector2::new(12., 34.))).unwrap();
+        assert!((world - Vector2::new(12., 34.)).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn zero_zoom_has_no_inverse () {
+        let mut camera = Camera2D::new((800, 600));
+        camera.zoom_at(Vector2::new(100., 100.), 0.);
+        camera.pan_screen(10., 10.);
+        assert_eq!(camera.zoom, 0.);
+        assert_eq!(camera.position, Vector2::new(400., 300.));
+        assert_eq!(camera.screen_to_world(Vector2::new(0., 0.)), None);
+    }
+}
@@ -0,0 +1,156 @@
+use glow::*;
+use std::sync::Arc;
+
+use cgmath::SquareMatrix;
+use lyon::math::rect;
+use lyon::path::{builder::*, Path, Winding};
+use lyon::tessellation::FillOptions;
+
+use crate::{
+    compile_shader, Color, ColorUniforms, Mesh, Program, ProjectionUniforms, ShaderError, Shape, Transform2D, TransformUniforms, Uniforms,
+    SOLID_FRAGMENT_SHADER, SOLID_VERTEX_SHADER
+};
+
+enum Clip {
+    // the scissor box that was active before this one
+    Rect(Option<[i32; 4]>),
+    Path
+}
+
+/// Nested clipping regions, each one intersecting the ones below it. Rectangles use the scissor
+/// test and are cheap, paths are drawn into the stencil buffer, so the framebuffer needs one
+/// (`RenderTarget::new` with `depth_stencil`, or the default framebuffer of `App`) and at most
+/// 255 path clips can be nested. Pushing and popping changes GL state that every draw call
+/// respects until the stack is empty again.
+pub struct ClipStack {
+    clips: Vec<Clip>,
+    scissor: Option<[i32; 4]>,
+    // path clips currently pushed, the stencil value of the pixels inside all of them
+    depth: u8,
+    program: Program,
+    // covers the whole viewport in clip space, to undo a path clip without drawing it again
+    fullscreen: Mesh,
+    gl: Arc<Context>
+}
+
+impl ClipStack {
+    pub unsafe fn new (gl: Arc<Context>) -> Result<Self, ShaderError> {
+        let program = compile_shader(&gl, SOLID_VERTEX_SHADER, SOLID_FRAGMENT_SHADER)?;
+
+        let mut builder = Path::builder();
+        builder.add_rectangle(&rect(-1., -1., 2., 2.), Winding::Positive);
+        let fullscreen = Mesh::new(gl.clone(), &builder.build(), &FillOptions::default()).unwrap();
+
+        Ok(Self {
+            clips: Vec::new(),
+            scissor: None,
+            depth: 0,
+            program,
+            fullscreen,
+            gl
+        })
+    }
+
+    /// Clips to a rectangle in framebuffer pixels, y down from the top of the current viewport.
+    /// It isn't affected by transforms or cameras.
+    pub unsafe fn push_clip_rect (&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let mut viewport = [0; 4];
+        self.gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+
+        // GL counts scissor rows from the bottom
+        let left = viewport[0] + x.floor() as i32;
+        let right = viewport[0] + (x + width).ceil() as i32;
+        let top = viewport[1] + viewport[3] - y.floor() as i32;
+        let bottom = viewport[1] + viewport[3] - (y + height).ceil() as i32;
+        let mut scissor = [left, bottom, right, top];
+        if let Some([left, bottom, right, top]) = self.scissor {
+            scissor = [scissor[0].max(left), scissor[1].max(bottom), scissor[2].min(right), scissor[3].min(top)];
+        }
+
+        self.clips.push(Clip::Rect(self.scissor));
+        self.set_scissor(Some(scissor));
+    }
+
+    /// Clips to the filled area of a shape, drawn with `transform` and `projection` like `draw_with`.
+    /// Use a `PathShape` to clip to an arbitrary path.
+    pub unsafe fn push_clip_path<S: Shape + ?Sized> (&mut self, mask: &S, transform: impl Into<Transform2D>, projection: impl Into<ProjectionUniforms>) {
+        self.push_clip_mesh(mask.mesh(), transform, projection)
+    }
+
+    pub unsafe fn push_clip_mesh (&mut self, mask: &Mesh, transform: impl Into<Transform2D>, projection: impl Into<ProjectionUniforms>) {
+        assert!(self.depth < u8::MAX, "too many nested path clips");
+        // only pixels inside every clip so far move one level deeper
+        self.draw_mask(mask, transform.into(), projection.into(), glow::INCR);
+        self.depth += 1;
+        self.clips.push(Clip::Path);
+        self.set_stencil();
+    }
+
+    /// Removes the most recent clip, restoring the region from before it was pushed.
+    pub unsafe fn pop (&mut self) {
+        match self.clips.pop() {
+            Some(Clip::Rect(previous)) => self.set_scissor(previous),
+            Some(Clip::Path) => {
+                // every pixel at the current depth got there through this clip
+                self.draw_mask(&self.fullscreen, Transform2D::identity(), ProjectionUniforms::from_matrix(cgmath::Matrix4::identity()), glow::DECR);
+                self.depth -= 1;
+                self.set_stencil();
+            },
+            None => {}
+        }
+    }
+
+    /// Drops every clip and zeroes the stencil buffer, for the start of a frame.
+    pub unsafe fn reset (&mut self) {
+        self.clips.clear();
+        self.set_scissor(None);
+        self.depth = 0;
+        self.set_stencil();
+        self.gl.clear_stencil(0);
+        self.gl.clear(glow::STENCIL_BUFFER_BIT);
+    }
+
+    pub fn len (&self) -> usize {
+        self.clips.len()
+    }
+
+    pub fn is_empty (&self) -> bool {
+        self.clips.is_empty()
+    }
+
+    unsafe fn draw_mask (&self, mask: &Mesh, transform: Transform2D, projection: ProjectionUniforms, operation: u32) {
+        self.gl.enable(glow::STENCIL_TEST);
+        self.gl.stencil_mask(0xff);
+        self.gl.stencil_func(glow::EQUAL, self.depth as i32, 0xff);
+        self.gl.stencil_op(glow::KEEP, glow::KEEP, operation);
+        self.gl.color_mask(false, false, false, false);
+        let uniforms: Vec<Box<dyn Uniforms>> = vec![
+            Box::new(projection),
+            Box::new(TransformUniforms::from(transform)),
+            Box::new(ColorUniforms::from(Color::WHITE))
+        ];
+        mask.render_fill(&self.program, uniforms, transform, projection);
+        self.gl.color_mask(true, true, true, true);
+    }
+
+    unsafe fn set_stencil (&self) {
+        if self.depth == 0 {
+            self.gl.disable(glow::STENCIL_TEST);
+        } else {
+            self.gl.enable(glow::STENCIL_TEST);
+            self.gl.stencil_func(glow::EQUAL, self.depth as i32, 0xff);
+            self.gl.stencil_op(glow::KEEP, glow::KEEP, glow::KEEP);
+        }
+    }
+
+    unsafe fn set_scissor (&mut self, scissor: Option<[i32; 4]>) {
+        self.scissor = scissor;
+        match scissor {
+            Some([left, bottom, right, top]) => {
+                self.gl.enable(glow::SCISSOR_TEST);
+                self.gl.scissor(left, bottom, (right - left).max(0), (top - bottom).max(0));
+            },
+            None => self.gl.disable(glow::SCISSOR_TEST)
+        }
+    }
+}
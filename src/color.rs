@@ -0,0 +1,172 @@
+use glow::*;
+use std::fmt;
+
+use crate::ColorUniforms;
+
+/// A straight (not premultiplied) RGBA color with components in 0..=1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(1., 1., 1.);
+    pub const BLACK: Color = Color::rgb(0., 0., 0.);
+    pub const TRANSPARENT: Color = Color::new(0., 0., 0., 0.);
+
+    pub const fn new (r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self {
+            r,
+            g,
+            b,
+            a
+        }
+    }
+
+    pub const fn rgb (r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b, 1.)
+    }
+
+    pub fn from_rgba8 (r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new(r as f32 / 255., g as f32 / 255., b as f32 / 255., a as f32 / 255.)
+    }
+
+    /// Parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`, the `#` is optional.
+    pub fn from_hex (hex: &str) -> Result<Self, ParseColorError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        // from_str_radix alone would also take a sign, and slicing needs ASCII
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseColorError(hex.to_string()));
+        }
+        let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&digits[range], 16).map_err(|_| ParseColorError(hex.to_string()));
+        // a single digit d stands for dd
+        let short = |index: usize| channel(index..index + 1).map(|value| value * 17);
+
+        match digits.len() {
+            3 => Ok(Self::from_rgba8(short(0)?, short(1)?, short(2)?, 255)),
+            4 => Ok(Self::from_rgba8(short(0)?, short(1)?, short(2)?, short(3)?)),
+            6 => Ok(Self::from_rgba8(channel(0..2)?, channel(2..4)?, channel(4..6)?, 255)),
+            8 => Ok(Self::from_rgba8(channel(0..2)?, channel(2..4)?, channel(4..6)?, channel(6..8)?)),
+            _ => Err(ParseColorError(hex.to_string()))
+        }
+    }
+
+    /// Hue in degrees, saturation and lightness in 0..=1.
+    pub fn from_hsl (hue: f32, saturation: f32, lightness: f32) -> Self {
+        Self::from_hsla(hue, saturation, lightness, 1.)
+    }
+
+    pub fn from_hsla (hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Self {
+        let hue = hue.rem_euclid(360.) / 60.;
+        let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+        let x = chroma * (1. - (hue % 2. - 1.).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.),
+            1 => (x, chroma, 0.),
+            2 => (0., chroma, x),
+            3 => (0., x, chroma),
+            4 => (x, 0., chroma),
+            _ => (chroma, 0., x)
+        };
+        let m = lightness - chroma / 2.;
+        Self::new(r + m, g + m, b + m, alpha)
+    }
+
+    pub fn with_alpha (mut self, alpha: f32) -> Self {
+        self.a = alpha;
+        self
+    }
+
+    /// The color with r, g and b multiplied by alpha, to draw with `BlendMode::Premultiplied`.
+    pub fn premultiplied (&self) -> Self {
+        Self::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    pub fn to_array (&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from(color: [f32; 4]) -> Self {
+        Self::new(color[0], color[1], color[2], color[3])
+    }
+}
+
+impl From<Color> for ColorUniforms {
+    fn from(color: Color) -> Self {
+        Self {
+            color: color.to_array()
+        }
+    }
+}
+
+impl From<ColorUniforms> for Color {
+    fn from(color: ColorUniforms) -> Self {
+        Self::from(color.color)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid hex color: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+/// How drawn pixels combine with what's already in the framebuffer. The shaders output straight
+/// alpha; `Multiply` and `Screen` are exact for opaque colors and an approximation for translucent ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Additive,
+    Multiply,
+    Screen,
+    // for colors and textures that are already premultiplied, like the contents of a RenderTarget
+    Premultiplied
+}
+
+impl BlendMode {
+    /// Enables blending and sets the blend functions. Alpha always accumulates as coverage,
+    /// so offscreen targets end up with a usable alpha channel.
+    pub unsafe fn apply (&self, gl: &Context) {
+        let (source, destination) = match self {
+            BlendMode::Normal => (glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (glow::SRC_ALPHA, glow::ONE),
+            BlendMode::Multiply => (glow::DST_COLOR, glow::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Screen => (glow::ONE, glow::ONE_MINUS_SRC_COLOR),
+            BlendMode::Premultiplied => (glow::ONE, glow::ONE_MINUS_SRC_ALPHA)
+        };
+        gl.enable(glow::BLEND);
+        gl.blend_equation(glow::FUNC_ADD);
+        gl.blend_func_separate(source, destination, glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_hex_form () {
+        assert_eq!(Color::from_hex("#f80"), Ok(Color::from_rgba8(255, 136, 0, 255)));
+        assert_eq!(Color::from_hex("f808"), Ok(Color::from_rgba8(255, 136, 0, 136)));
+        assert_eq!(Color::from_hex("#ff8000"), Ok(Color::from_rgba8(255, 128, 0, 255)));
+        assert_eq!(Color::from_hex("#FF800080"), Ok(Color::from_rgba8(255, 128, 0, 128)));
+    }
+
+    #[test]
+    fn rejects_invalid_hex () {
+        for hex in ["", "#", "#ff", "#fffff", "#ggg", "#+f+f+f", "#-1-1-1", "#ff 000", "#ffé0", "##fff"].iter() {
+            assert_eq!(Color::from_hex(hex), Err(ParseColorError(hex.to_string())), "{}", hex);
+        }
+    }
+}
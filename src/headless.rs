@@ -0,0 +1,295 @@
+//! Rendering without a window or display, through EGL. On machines without a GPU Mesa's
+//! llvmpipe software rasterizer is used, which makes it usable for golden image tests on CI.
+
+use glow::*;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use khronos_egl as egl;
+
+use crate::{LeakReport, RenderTarget, ResourceRegistry};
+
+// from EGL_MESA_platform_surfaceless, not exported by khronos-egl
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    Load(String),
+    Egl(egl::Error),
+    NoDisplay,
+    NoConfig,
+    Framebuffer(String)
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadlessError::Load(message) => write!(f, "cannot load libEGL: {}", message),
+            HeadlessError::Egl(error) => write!(f, "EGL error: {}", error),
+            HeadlessError::NoDisplay => write!(f, "no EGL display available"),
+            HeadlessError::NoConfig => write!(f, "no EGL config supports offscreen OpenGL rendering"),
+            HeadlessError::Framebuffer(message) => write!(f, "cannot create the offscreen target: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<egl::Error> for HeadlessError {
+    fn from(error: egl::Error) -> Self {
+        HeadlessError::Egl(error)
+    }
+}
+
+/// An OpenGL context with no window, rendering into a `RenderTarget` of the requested size,
+/// which stays bound so shapes can be drawn right away.
+pub struct HeadlessContext {
+    target: Option<RenderTarget>,
+    gl: Arc<Context>,
+    egl: egl::DynamicInstance<egl::EGL1_4>,
+    display: egl::Display,
+    context: egl::Context,
+    surface: egl::Surface,
+    destroyed: bool
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            self.destroy();
+        }
+    }
+}
+
+impl HeadlessContext {
+    /// Prefers a desktop GL 3.3 core context and falls back to GLES 3.0.
+    pub unsafe fn new (width: u32, height: u32) -> Result<Self, HeadlessError> {
+        let egl = egl::DynamicInstance::<egl::EGL1_4>::load_required().map_err(|error| HeadlessError::Load(error.to_string()))?;
+        let display = Self::display(&egl).ok_or(HeadlessError::NoDisplay)?;
+        egl.initialize(display)?;
+
+        let created = Self::create_context(&egl, display, egl::OPENGL_API, egl::OPENGL_BIT, &[
+            egl::CONTEXT_MAJOR_VERSION, 3,
+            egl::CONTEXT_MINOR_VERSION, 3,
+            egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::NONE
+        ]).or_else(|_| Self::create_context(&egl, display, egl::OPENGL_ES_API, egl::OPENGL_ES2_BIT, &[
+            egl::CONTEXT_MAJOR_VERSION, 3,
+            egl::NONE
+        ]));
+        let (context, surface) = match created {
+            Ok(created) => created,
+            Err(error) => {
+                let _ = egl.terminate(display);
+                return Err(error);
+            }
+        };
+
+        let gl = Arc::new(Context::from_loader_function(|name| {
+            egl.get_proc_address(name).map_or(std::ptr::null(), |address| address as *const _)
+        }));
+
+        let mut headless = Self {
+            target: None,
+            gl: gl.clone(),
+            egl,
+            display,
+            context,
+            surface,
+            destroyed: false
+        };
+        let target = RenderTarget::new(gl, width, height, true).map_err(HeadlessError::Framebuffer)?;
+        target.bind();
+        headless.target = Some(target);
+        Ok(headless)
+    }
+
+    // the surfaceless platform needs neither X11 nor Wayland nor a DRM device
+    unsafe fn display (egl: &egl::DynamicInstance<egl::EGL1_4>) -> Option<egl::Display> {
+        let extensions = egl.query_string(None, egl::EXTENSIONS).map(|extensions| extensions.to_string_lossy().into_owned()).unwrap_or_default();
+        if extensions.contains("EGL_MESA_platform_surfaceless") {
+            if let Some(get_platform_display) = egl.get_proc_address("eglGetPlatformDisplayEXT") {
+                let get_platform_display: extern "C" fn(egl::Enum, *mut std::ffi::c_void, *const egl::Int) -> egl::EGLDisplay = std::mem::transmute(get_platform_display);
+                let display = get_platform_display(PLATFORM_SURFACELESS_MESA, std::ptr::null_mut(), [egl::NONE].as_ptr());
+                if !display.is_null() {
+                    return Some(egl::Display::from_ptr(display));
+                }
+            }
+        }
+        egl.get_display(egl::DEFAULT_DISPLAY)
+    }
+
+    unsafe fn create_context (egl: &egl::DynamicInstance<egl::EGL1_4>, display: egl::Display, api: egl::Enum, renderable: egl::Int, attributes: &[egl::Int]) -> Result<(egl::Context, egl::Surface), HeadlessError> {
+        egl.bind_api(api)?;
+        let config = egl.choose_first_config(display, &[
+            egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+            egl::RENDERABLE_TYPE, renderable,
+            egl::RED_SIZE, 8,
+            egl::GREEN_SIZE, 8,
+            egl::BLUE_SIZE, 8,
+            egl::ALPHA_SIZE, 8,
+            egl::NONE
+        ])?.ok_or(HeadlessError::NoConfig)?;
+
+        let context = egl.create_context(display, config, None, attributes)?;
+        // rendering goes to the RenderTarget, the pbuffer only exists to make the context current
+        let surface = match egl.create_pbuffer_surface(display, config, &[egl::WIDTH, 1, egl::HEIGHT, 1, egl::NONE]) {
+            Ok(surface) => surface,
+            Err(error) => {
+                let _ = egl.destroy_context(display, context);
+                return Err(error.into());
+            }
+        };
+        if let Err(error) = egl.make_current(display, Some(surface), Some(surface), Some(context)) {
+            let _ = egl.destroy_surface(display, surface);
+            let _ = egl.destroy_context(display, context);
+            return Err(error.into());
+        }
+        Ok((context, surface))
+    }
+
+    pub fn gl (&self) -> &Arc<Context> {
+        &self.gl
+    }
+
+    pub fn target (&self) -> &RenderTarget {
+        self.target.as_ref().unwrap()
+    }
+
+    pub fn size (&self) -> (u32, u32) {
+        self.target().size()
+    }
+
+    /// Destroys the context and reports the GL objects that outlived it. Dropping the context
+    /// does the same and discards the report.
+    pub fn close (mut self) -> LeakReport {
+        unsafe { self.destroy() }
+    }
+
+    unsafe fn destroy (&mut self) -> LeakReport {
+        if self.destroyed {
+            return LeakReport::default();
+        }
+        self.destroyed = true;
+
+        // the target's GL objects go while the context is still current
+        self.target.take();
+        let leaks = ResourceRegistry::destroy(&self.gl);
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_surface(self.display, self.surface);
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.terminate(self.display);
+        leaks
+    }
+
+    /// The rendered image as tightly packed RGBA, top row first. Always reads the context's own
+    /// target, whatever framebuffer is bound.
+    pub unsafe fn read_pixels (&self) -> Vec<u8> {
+        self.target().read_pixels()
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Image(image::ImageError),
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32)
+    }
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GoldenError::Image(error) => write!(f, "cannot read the reference image: {}", error),
+            GoldenError::SizeMismatch { expected, actual } => write!(f, "reference is {}x{} but the image is {}x{}", expected.0, expected.1, actual.0, actual.1)
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<image::ImageError> for GoldenError {
+    fn from(error: image::ImageError) -> Self {
+        GoldenError::Image(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageComparison {
+    // pixels with at least one channel off by more than the tolerance
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    pub total_pixels: usize
+}
+
+impl ImageComparison {
+    pub fn is_match (&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+/// Compares RGBA pixels (top row first, as returned by `read_pixels`) against a reference PNG.
+pub fn compare_with_png<P: AsRef<Path>> (pixels: &[u8], width: u32, height: u32, reference: P, tolerance: u8) -> Result<ImageComparison, GoldenError> {
+    let reference = image::open(reference)?.into_rgba8();
+    if reference.dimensions() != (width, height) {
+        return Err(GoldenError::SizeMismatch {
+            expected: reference.dimensions(),
+            actual: (width, height)
+        });
+    }
+    Ok(compare_pixels(pixels, reference.as_raw(), tolerance))
+}
+
+pub fn compare_pixels (actual: &[u8], expected: &[u8], tolerance: u8) -> ImageComparison {
+    assert_eq!(actual.len(), expected.len(), "images differ in size");
+
+    let mut comparison = ImageComparison {
+        mismatched_pixels: 0,
+        max_difference: 0,
+        total_pixels: actual.len() / 4
+    };
+    for (a, b) in actual.chunks_exact(4).zip(expected.chunks_exact(4)) {
+        let difference = a.iter().zip(b).map(|(a, b)| (*a as i16 - *b as i16).unsigned_abs() as u8).max().unwrap_or(0);
+        comparison.max_difference = comparison.max_difference.max(difference);
+        if difference > tolerance {
+            comparison.mismatched_pixels += 1;
+        }
+    }
+    comparison
+}
+
+/// Writes RGBA pixels as a PNG, to create or update reference images.
+pub fn save_png<P: AsRef<Path>> (path: P, pixels: &[u8], width: u32, height: u32) -> Result<(), image::ImageError> {
+    image::save_buffer(path, pixels, width, height, image::ColorType::Rgba8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_within_tolerance () {
+        let comparison = compare_pixels(&[10, 20, 30, 255, 0, 0, 0, 255], &[12, 20, 28, 255, 0, 0, 0, 255], 2);
+        assert!(comparison.is_match());
+        assert_eq!(comparison.max_difference, 2);
+        assert_eq!(comparison.total_pixels, 2);
+    }
+
+    #[test]
+    fn counts_mismatched_pixels () {
+        let actual = [0, 0, 0, 255, 100, 0, 0, 255, 0, 0, 0, 0];
+        let expected = [0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255];
+        let comparison = compare_pixels(&actual, &expected, 10);
+        assert!(!comparison.is_match());
+        assert_eq!(comparison.mismatched_pixels, 2);
+        assert_eq!(comparison.max_difference, 255);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_images_of_different_sizes () {
+        compare_pixels(&[0; 8], &[0; 4], 0);
+    }
+}
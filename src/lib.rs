@@ -1,17 +1,125 @@
+#![allow(clippy::missing_safety_doc)]
+
 use glow::*;
 use std::sync::Arc;
 
+use lyon::math::{point, rect};
+use lyon::path::{builder::*, Winding};
+use lyon::tessellation::{FillOptions, TessellationError};
+
+mod antialias;
+#[cfg(feature = "windowing")]
+mod app;
+mod batch;
+mod camera;
+mod clip;
+mod color;
+#[cfg(feature = "headless")]
+pub mod headless;
+mod mesh;
+mod paint;
+mod resources;
+mod scene;
+mod shader;
+mod target;
+#[cfg(feature = "text")]
+mod text;
+mod texture;
+mod transform;
+
+pub use antialias::{fringe, Antialiasing, FringeGeometry, FringeVertex};
+#[cfg(feature = "windowing")]
+pub use app::{App, AppError, Frame, WindowOptions};
+pub use batch::Batch;
+pub use camera::Camera2D;
+pub use clip::ClipStack;
+pub use color::{BlendMode, Color, ParseColorError};
+pub use mesh::{Geometry, Instance, Mesh, Tolerance, DEFAULT_TOLERANCE, MIN_TOLERANCE};
+pub use paint::{ColorStop, Gradient, GradientKind, Paint, SpreadMode, MAX_COLOR_STOPS};
+pub use resources::{
+    GLBuffer, GLFramebuffer, GLProgram, GLRenderbuffer, GLResource, GLTexture, GLVertexArray, Handle, LeakReport, ResourceKind, ResourceRegistry
+};
+pub use scene::{Drawable, Node, NodeId, RenderContext, Scene};
+pub use target::{read_pixels, RenderTarget};
+pub use texture::{FilterMode, Texture, WrapMode};
+pub use transform::Transform2D;
+#[cfg(feature = "text")]
+pub use text::{measure_text, Font, GlyphAtlas, Text, TextAlign, TextLayout};
+#[cfg(feature = "image")]
+pub use image;
+#[cfg(feature = "windowing")]
+pub use glutin;
+pub use shader::{
+    compile_shader, compile_shader_with_version, AttributeInfo, GlslVersion, Program, ShaderDiagnostic, ShaderError,
+    ShaderLibrary, ShaderStage, UniformInfo, ATTRIBUTE_COLOR, ATTRIBUTE_COVERAGE, ATTRIBUTE_INSTANCE_COLOR, ATTRIBUTE_INSTANCE_OFFSET,
+    ATTRIBUTE_INSTANCE_SCALE, ATTRIBUTE_POSITION, ATTRIBUTE_TEX_COORDS, BATCH_FRAGMENT_SHADER, BATCH_VERTEX_SHADER,
+    INSTANCED_VERTEX_SHADER, PAINT_FRAGMENT_SHADER, PAINT_VERTEX_SHADER,
+    RADIAL_GRADIENT_FRAGMENT_SHADER, RADIAL_GRADIENT_VERTEX_SHADER, SOLID_FRAGMENT_SHADER, SOLID_VERTEX_SHADER,
+    TEXTURED_FRAGMENT_SHADER, TEXTURED_VERTEX_SHADER, TEXT_FRAGMENT_SHADER, TEXT_VERTEX_SHADER
+};
+pub use lyon;
+pub use lyon::path::{FillRule, Path};
+pub use lyon::tessellation::{LineCap, LineJoin, StrokeOptions};
+
+/// Corner style of a `Rectangle`. Radii are clamped to half the width/height, so `Round(f32::MAX)` gives a pill.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CornerType {
-    Round,
+    Round(f32),
+    Corners(CornerRadii),
     Hard
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_left: f32,
+    pub bottom_right: f32
+}
+
+impl CornerRadii {
+    // clockwise from the top left, same order as CSS border-radius
+    pub fn new (top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> Self {
+        Self {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right
+        }
+    }
+
+    pub fn uniform (radius: f32) -> Self {
+        Self::new(radius, radius, radius, radius)
+    }
+
+    pub fn clamped (&self, width: f32, height: f32) -> Self {
+        let max = (width.abs() / 2.).min(height.abs() / 2.);
+        let clamp = |radius: f32| radius.max(0.).min(max);
+        Self {
+            top_left: clamp(self.top_left),
+            top_right: clamp(self.top_right),
+            bottom_left: clamp(self.bottom_left),
+            bottom_right: clamp(self.bottom_right)
+        }
+    }
+}
+
+impl CornerType {
+    pub fn radii (&self, width: f32, height: f32) -> CornerRadii {
+        match self {
+            CornerType::Round(radius) => CornerRadii::uniform(*radius),
+            CornerType::Corners(radii) => *radii,
+            CornerType::Hard => CornerRadii::default()
+        }.clamped(width, height)
+    }
+}
+
 pub trait Uniforms {
-    unsafe fn set_uniforms (&self, gl: &Context, program: u32);
+    unsafe fn set_uniforms (&self, program: &Program);
 }
 
 pub struct TransformUniforms {
-    transform: cgmath::Matrix4<f32>
+    pub(crate) transform: cgmath::Matrix4<f32>
 }
 
 impl TransformUniforms {
@@ -31,517 +139,620 @@ impl TransformUniforms {
     }
 }
 
+impl Default for TransformUniforms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Uniforms for TransformUniforms {
-    unsafe fn set_uniforms(&self, gl: &Context, program: u32) {
-        let location = gl.get_uniform_location(program, "transform").unwrap();
-        let data: &[f32; 16] = self.transform.as_ref();
-        gl.uniform_matrix_4_f32_slice(Some(&location), false, data);
+    unsafe fn set_uniforms(&self, program: &Program) {
+        program.set_mat4("transform", self.transform.as_ref());
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ColorUniforms {
-    color: [f32; 3]
+    pub(crate) color: [f32; 4]
 }
 
 impl Uniforms for ColorUniforms {
-    unsafe fn set_uniforms(&self, gl: &Context, program: u32) {
-        let location = gl.get_uniform_location(program, "ucolor").unwrap();
-        gl.uniform_3_f32_slice(Some(&location), &self.color);
+    unsafe fn set_uniforms(&self, program: &Program) {
+        program.set_vec4("ucolor", &self.color);
     }
 }
 
 impl ColorUniforms {
     pub fn new (r: f32, g: f32, b: f32,) -> Self {
         Self {
-            color: [r, g, b]
+            color: [r, g, b, 1.]
         }
     }
     
     pub fn new_from_8 (r: u8, g: u8, b: u8,) -> Self {
         Self {
-            color: [r as f32 / 255., g as f32 / 255., b as f32 / 255.]
+            color: [r as f32 / 255., g as f32 / 255., b as f32 / 255., 1.]
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ProjectionUniforms {
-    projection: cgmath::Matrix4<f32>
+    pub(crate) projection: cgmath::Matrix4<f32>,
+    // size in pixels of what's drawn to, for picking levels of detail without asking GL
+    viewport: Option<(u32, u32)>
 }
 
 impl ProjectionUniforms {
     pub fn new (size: (u32, u32)) -> Self {
         Self {
-            projection: cgmath::ortho(0., size.0 as f32, size.1 as f32, 0., 0., 1.)
+            projection: cgmath::ortho(0., size.0 as f32, size.1 as f32, 0., 0., 1.),
+            viewport: Some(size)
+        }
+    }
+
+    /// Without `with_viewport`, meshes with `Tolerance::Auto` are drawn at their base tolerance.
+    pub fn from_matrix (projection: cgmath::Matrix4<f32>) -> Self {
+        Self {
+            projection,
+            viewport: None
         }
     }
+
+    pub fn with_viewport (mut self, size: (u32, u32)) -> Self {
+        self.viewport = Some(size);
+        self
+    }
+
+    pub fn matrix (&self) -> cgmath::Matrix4<f32> {
+        self.projection
+    }
+
+    pub fn viewport (&self) -> Option<(u32, u32)> {
+        self.viewport
+    }
+}
+
+impl From<(u32, u32)> for ProjectionUniforms {
+    fn from(size: (u32, u32)) -> Self {
+        Self::new(size)
+    }
 }
 
 impl Uniforms for ProjectionUniforms {
-    unsafe fn set_uniforms(&self, gl: &Context, program: u32) {
-        let location = gl.get_uniform_location(program, "projection").unwrap();
-        let data: &[f32; 16] = self.projection.as_ref();
-        gl.uniform_matrix_4_f32_slice(Some(&location), false, data);
+    unsafe fn set_uniforms(&self, program: &Program) {
+        program.set_mat4("projection", self.projection.as_ref());
     }
 }
 
 pub struct Circle {
-    vertex_array: u32,
-    vertex_buffer: u32,
-    index_buffer: u32,
-    indices: usize,
-    pub radius: f32,
-    gl: Arc<Context>
-}
-
-impl Drop for Circle {
-    fn drop(&mut self) {
-        unsafe {
-            self.gl.delete_vertex_array(self.vertex_array);
-            self.gl.delete_buffer(self.vertex_buffer);
-            self.gl.delete_buffer(self.index_buffer);
-        }
-    }
+    mesh: Mesh,
+    radius: f32
 }
 
 impl Circle {
-    pub unsafe fn new (gl: Arc<Context>, radius: f32) -> Result<Self, lyon::tessellation::TessellationError> {
-        use lyon::math::Point;
-        use lyon::path::{builder::*, Winding};
-        use lyon::tessellation::{FillTessellator, FillOptions, VertexBuffers};
-        use lyon::tessellation::geometry_builder::simple_builder;
-
-        let mut geometry: VertexBuffers<Point, u16> = VertexBuffers::new();
-        let mut geometry_builder = simple_builder(&mut geometry);
-        let options = FillOptions::tolerance(0.1);
-        let mut tessellator = FillTessellator::new();
-    
-        let mut builder = tessellator.builder(
-            &options,
-            &mut geometry_builder,
-        );
-    
-        builder.add_circle(
-            Point::new(0., 0.),
-            radius,
-            Winding::Positive
-        );
-    
-        builder.build()?;
+    pub unsafe fn new (gl: Arc<Context>, radius: f32) -> Result<Self, TessellationError> {
+        Self::with_tolerance(gl, radius, Tolerance::default())
+    }
 
-        let vertex_array = gl.create_vertex_array().unwrap();
-        gl.bind_vertex_array(Some(vertex_array));
-    
-        let vertex_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+    pub unsafe fn with_tolerance (gl: Arc<Context>, radius: f32, tolerance: impl Into<Tolerance>) -> Result<Self, TessellationError> {
+        Ok(Self {
+            mesh: fill_mesh(gl, &circle_path(radius), tolerance.into())?,
+            radius
+        })
+    }
 
-        let index_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
-    
-        gl.enable_vertex_attrib_array(0);
-        gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
-    
-        let mut vertices = Vec::new();
-        for vertex in geometry.vertices {
-            vertices.push(vertex.x,);
-            vertices.push(vertex.y);
-        }
-    
-        let mut vertex_buffer_data = Vec::<u8>::with_capacity(vertices.len() * 4);
-        for float in vertices.iter() {
-            vertex_buffer_data.extend_from_slice(&float.to_le_bytes());
-        }
+    pub fn radius (&self) -> f32 {
+        self.radius
+    }
 
-    
-        let mut index_buffer_data = Vec::<u8>::with_capacity(geometry.indices.len() * 2);
-        for n in geometry.indices.iter() {
-            index_buffer_data.extend_from_slice(&n.to_le_bytes());
-        }
-    
-        gl.buffer_data_u8_slice(
-            glow::ARRAY_BUFFER,
-            vertex_buffer_data.as_ref(),
-            glow::DYNAMIC_DRAW,
-        );
-
-        gl.buffer_data_u8_slice(
-            glow::ELEMENT_ARRAY_BUFFER,
-            index_buffer_data.as_ref(),
-            glow::DYNAMIC_DRAW,
-        );
+    /// The mesh follows on the next draw.
+    pub fn set_radius (&mut self, radius: f32) {
+        self.radius = radius;
+        self.mesh.replace_path(circle_path(radius));
+    }
 
-        Ok(Self {
-            vertex_array,
-            vertex_buffer,
-            index_buffer,
-            indices: geometry.indices.len(),
-            radius,
-            gl
-        })
+    pub fn set_tolerance (&mut self, tolerance: impl Into<Tolerance>) -> Result<(), TessellationError> {
+        self.mesh.set_tolerance(tolerance.into())
     }
 
-    pub fn draw_with(&self, program: u32, position: cgmath::Vector2<f32>, color: ColorUniforms, resolution: (u32, u32)) {
-        let mut uniforms: Vec<Box<dyn Uniforms>> = Vec::new();
-        uniforms.push(Box::new(ProjectionUniforms::new(resolution)));
-        uniforms.push(Box::new({
-            let mut t = TransformUniforms::new();
-            t.translate(position.x, position.y);
-            t
-        }));
-        uniforms.push(Box::new(color));
-        unsafe { self.render(program, uniforms) }
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
+        self.mesh.draw_with(program, transform, color, projection)
     }
 }
 
 impl GLObject for Circle {
-    unsafe fn render(&self, program: u32, uniforms: Vec<Box<dyn Uniforms>>) {
-        self.gl.use_program(Some(program));
-        self.gl.bind_vertex_array(Some(self.vertex_array));
-        self.gl.bind_buffer(ARRAY_BUFFER, Some(self.vertex_buffer));
-
-        self.gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(self.index_buffer));
-        for uniform in uniforms {
-            uniform.set_uniforms(&self.gl, program); // set up all the uniforms for our shader
-        }
-        self.gl.draw_elements(TRIANGLES, self.indices as i32, UNSIGNED_SHORT, 0);
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.mesh.render(program, uniforms)
+    }
+}
+
+impl Shape for Circle {
+    fn path (&self) -> Path {
+        circle_path(self.radius)
+    }
+
+    fn mesh (&self) -> &Mesh {
+        &self.mesh
     }
 }
 
 //
 #[derive(Debug, Clone)]
 pub struct Rectangle {
-    vertex_array: u32,
-    vertex_buffer: u32,
-    index_buffer: u32,
-    indices: usize,
-    pub width: f32,
-    pub height: f32, 
-    gl: Arc<Context>
-}
-
-impl Drop for Rectangle {
-    fn drop(&mut self) {
-        unsafe {
-            self.gl.delete_vertex_array(self.vertex_array);
-            self.gl.delete_buffer(self.vertex_buffer);
-            self.gl.delete_buffer(self.index_buffer);
-        }
-    }
+    mesh: Mesh,
+    width: f32,
+    height: f32,
+    kind: CornerType
 }
 
 impl Rectangle {
     pub unsafe fn new (gl: Arc<Context>, width: f32, height: f32, kind: CornerType) -> Self {
-        use lyon::math::{rect, Point};
-        use lyon::path::{builder::*, Winding};
-        use lyon::tessellation::{FillTessellator, FillOptions, VertexBuffers};
-        use lyon::tessellation::geometry_builder::simple_builder;
-
-        let mut geometry: VertexBuffers<Point, u16> = VertexBuffers::new();
-        let mut geometry_builder = simple_builder(&mut geometry);
-        let options = FillOptions::tolerance(0.1);
-        let mut tessellator = FillTessellator::new();
-    
-        let mut builder = tessellator.builder(
-            &options,
-            &mut geometry_builder,
-        );
+        Self::with_tolerance(gl, width, height, kind, Tolerance::default()).unwrap()
+    }
 
-        match kind {
-            CornerType::Hard => builder.add_rectangle(
-                &rect(0.0, 0.0, width, height),
-                Winding::Positive
-            ),
-            CornerType::Round => builder.add_rounded_rectangle(
-                &rect(0.0, 0.0, width, height),
-                &BorderRadii {
-                    top_left: 4.5,
-                    top_right: 4.5,
-                    bottom_left: 4.5,
-                    bottom_right: 4.5,
-                },
-                Winding::Positive
-            )
-        }
-    
-        builder.build().unwrap();
+    pub unsafe fn with_tolerance (gl: Arc<Context>, width: f32, height: f32, kind: CornerType, tolerance: impl Into<Tolerance>) -> Result<Self, TessellationError> {
+        Ok(Self {
+            mesh: fill_mesh(gl, &rectangle_path(width, height, kind), tolerance.into())?,
+            width,
+            height,
+            kind
+        })
+    }
 
-        let vertex_array = gl.create_vertex_array().unwrap();
-        gl.bind_vertex_array(Some(vertex_array));
-    
-        let vertex_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+    pub fn update (&mut self, width: f32, height: f32, kind: CornerType) {
+        self.width = width;
+        self.height = height;
+        self.set_kind(kind);
+    }
 
-        let index_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
-    
-        gl.enable_vertex_attrib_array(0);
-        gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
-    
-        let mut vertices = Vec::new();
-        for vertex in geometry.vertices {
-            vertices.push(vertex.x,);
-            vertices.push(vertex.y);
-        }
-    
-        let mut vertex_buffer_data = Vec::<u8>::with_capacity(vertices.len() * 4);
-        for float in vertices.iter() {
-            vertex_buffer_data.extend_from_slice(&float.to_le_bytes());
-        }
+    pub fn width (&self) -> f32 {
+        self.width
+    }
 
-    
-        let mut index_buffer_data = Vec::<u8>::with_capacity(geometry.indices.len() * 2);
-        for n in geometry.indices.iter() {
-            index_buffer_data.extend_from_slice(&n.to_le_bytes());
-        }
-    
-        gl.buffer_data_u8_slice(
-            glow::ARRAY_BUFFER,
-            vertex_buffer_data.as_ref(),
-            glow::STATIC_DRAW,
-        );
+    pub fn height (&self) -> f32 {
+        self.height
+    }
 
-        gl.buffer_data_u8_slice(
-            glow::ELEMENT_ARRAY_BUFFER,
-            index_buffer_data.as_ref(),
-            glow::STATIC_DRAW,
-        );
+    /// The mesh follows on the next draw, like `set_kind`.
+    pub fn set_size (&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+        self.mesh.replace_path(self.path());
+    }
 
+    pub fn set_kind (&mut self, kind: CornerType) {
+        self.kind = kind;
+        self.mesh.replace_path(self.path());
+    }
 
-        Self {
-            vertex_array,
-            vertex_buffer,
-            index_buffer,
-            indices: geometry.indices.len(),
-            width,
-            height,
-            gl
-        }
+    /// Only rounded corners have curves to follow.
+    pub fn set_tolerance (&mut self, tolerance: impl Into<Tolerance>) -> Result<(), TessellationError> {
+        self.mesh.set_tolerance(tolerance.into())
     }
 
-    pub unsafe fn update (&mut self, width: f32, height: f32, kind: CornerType) {
-        use lyon::math::{rect, Point};
-        use lyon::path::{builder::*, Winding};
-        use lyon::tessellation::{FillTessellator, FillOptions, VertexBuffers};
-        use lyon::tessellation::geometry_builder::simple_builder;
+    pub fn kind (&self) -> CornerType {
+        self.kind
+    }
 
-        let mut geometry: VertexBuffers<Point, u16> = VertexBuffers::new();
-        let mut geometry_builder = simple_builder(&mut geometry);
-        let options = FillOptions::tolerance(0.1);
-        let mut tessellator = FillTessellator::new();
-    
-        let mut builder = tessellator.builder(
-            &options,
-            &mut geometry_builder,
-        );
-    
-        match kind {
-            CornerType::Hard => builder.add_rectangle(
-                &rect(0.0, 0.0, width, height),
-                Winding::Positive
-            ),
-            CornerType::Round => builder.add_rounded_rectangle(
-                &rect(0.0, 0.0, width, height),
-                &BorderRadii {
-                    top_left: 4.5,
-                    top_right: 4.5,
-                    bottom_left: 4.5,
-                    bottom_right: 4.5,
-                },
-                Winding::Positive
-            )
-        }
-    
-        builder.build().unwrap();
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
+        self.mesh.draw_with(program, transform, color, projection)
+    }
+}
 
-        self.gl.bind_vertex_array(Some(self.vertex_array));
-        self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vertex_buffer));
-        self.gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(self.index_buffer));
-    
-        self.gl.enable_vertex_attrib_array(0);
-        self.gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
-    
-        let mut vertices = Vec::new();
-        for vertex in geometry.vertices {
-            vertices.push(vertex.x,);
-            vertices.push(vertex.y);
-        }
-    
-        let mut vertex_buffer_data = Vec::<u8>::with_capacity(vertices.len() * 4);
-        for float in vertices.iter() {
-            vertex_buffer_data.extend_from_slice(&float.to_le_bytes());
-        }
+impl GLObject for Rectangle {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.mesh.render(program, uniforms)
+    }
+}
 
-    
-        let mut index_buffer_data = Vec::<u8>::with_capacity(geometry.indices.len() * 2);
-        for n in geometry.indices.iter() {
-            index_buffer_data.extend_from_slice(&n.to_le_bytes());
-        }
-    
-        self.gl.buffer_data_u8_slice(
-            glow::ARRAY_BUFFER,
-            vertex_buffer_data.as_ref(),
-            glow::STATIC_DRAW,
-        );
-
-        self.gl.buffer_data_u8_slice(
-            glow::ELEMENT_ARRAY_BUFFER,
-            index_buffer_data.as_ref(),
-            glow::STATIC_DRAW,
-        );
+impl Shape for Rectangle {
+    fn path (&self) -> Path {
+        rectangle_path(self.width, self.height, self.kind)
+    }
 
-        self.width = width;
-        self.height = height;
+    fn mesh (&self) -> &Mesh {
+        &self.mesh
+    }
+}
+//
+
+pub struct RadialGradient {
+    mesh: Mesh,
+    radius: f32
+}
+
+impl RadialGradient {
+    pub unsafe fn new (gl: Arc<Context>, radius: f32) -> Result<Self, TessellationError> {
+        Self::with_tolerance(gl, radius, Tolerance::default())
+    }
+
+    pub unsafe fn with_tolerance (gl: Arc<Context>, radius: f32, tolerance: impl Into<Tolerance>) -> Result<Self, TessellationError> {
+        Ok(Self {
+            mesh: fill_mesh(gl, &circle_path(radius), tolerance.into())?,
+            radius
+        })
+    }
+
+    pub fn radius (&self) -> f32 {
+        self.radius
+    }
+
+    /// The mesh follows on the next draw.
+    pub fn set_radius (&mut self, radius: f32) {
+        self.radius = radius;
+        self.mesh.replace_path(circle_path(radius));
+    }
+
+    pub fn set_tolerance (&mut self, tolerance: impl Into<Tolerance>) -> Result<(), TessellationError> {
+        self.mesh.set_tolerance(tolerance.into())
     }
 
-    pub fn draw_with(&self, program: u32, position: cgmath::Vector2<f32>, color: ColorUniforms, resolution: (u32, u32)) {
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
+        let (transform, projection) = (transform.into(), projection.into());
         let mut uniforms: Vec<Box<dyn Uniforms>> = Vec::new();
-        uniforms.push(Box::new(ProjectionUniforms::new(resolution)));
-        uniforms.push(Box::new({
-            let mut t = TransformUniforms::new();
-            t.translate(position.x, position.y);
-            t
-        }));
-        uniforms.push(Box::new(color));
-        unsafe { self.render(program, uniforms) }
+        uniforms.push(Box::new(projection));
+        uniforms.push(Box::new(TransformUniforms::from(transform)));
+        uniforms.push(Box::new(color.into()));
+        // the shader measures the distance in local coordinates, so the gradient follows any scale or skew
+        uniforms.push(Box::new(GenericVec2Uniform::new(String::from("center"), cgmath::vec2(0., 0.))));
+        uniforms.push(Box::new(GenericFloatUniform::new(String::from("range"), self.radius)));
+        unsafe { self.mesh.render_lod(program, uniforms, transform, projection) }
     }
 }
 
-impl GLObject for Rectangle {
-    unsafe fn render(&self, program: u32, uniforms: Vec<Box<dyn Uniforms>>) {
-        self.gl.use_program(Some(program));
-        self.gl.bind_vertex_array(Some(self.vertex_array));
-        self.gl.bind_buffer(ARRAY_BUFFER, Some(self.vertex_buffer));
-
-        self.gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(self.index_buffer));
-        for uniform in uniforms {
-            uniform.set_uniforms(&self.gl, program); // set up all the uniforms for our shader
-        }
-        self.gl.draw_elements(TRIANGLES, self.indices as i32, UNSIGNED_SHORT, 0);
+impl GLObject for RadialGradient {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.mesh.render(program, uniforms)
     }
 }
-//
 
-pub struct RadialGradient {
-    vertex_array: u32,
-    vertex_buffer: u32,
-    index_buffer: u32,
-    indices: usize,
-    pub radius: f32,
-    gl: Arc<Context>
-}
-
-impl Drop for RadialGradient {
-    fn drop(&mut self) {
-        unsafe {
-            self.gl.delete_vertex_array(self.vertex_array);
-            self.gl.delete_buffer(self.vertex_buffer);
-            self.gl.delete_buffer(self.index_buffer);
-        }
+impl Shape for RadialGradient {
+    fn path (&self) -> Path {
+        circle_path(self.radius)
+    }
+
+    fn mesh (&self) -> &Mesh {
+        &self.mesh
     }
 }
 
-impl RadialGradient {
-    pub unsafe fn new (gl: Arc<Context>, radius: f32) -> Result<Self, lyon::tessellation::TessellationError> {
-        use lyon::math::Point;
-        use lyon::path::{builder::*, Winding};
-        use lyon::tessellation::{FillTessellator, FillOptions, VertexBuffers};
-        use lyon::tessellation::geometry_builder::simple_builder;
-
-        let mut geometry: VertexBuffers<Point, u16> = VertexBuffers::new();
-        let mut geometry_builder = simple_builder(&mut geometry);
-        let options = FillOptions::tolerance(0.1);
-        let mut tessellator = FillTessellator::new();
-    
-        let mut builder = tessellator.builder(
-            &options,
-            &mut geometry_builder,
-        );
-    
-        builder.add_circle(
-            Point::new(0., 0.),
-            radius,
-            Winding::Positive
-        );
-    
-        builder.build()?;
+/// Any lyon path (lines, béziers, arcs, several subpaths) filled with the given rule and tolerance.
+pub struct PathShape {
+    mesh: Mesh,
+    path: Path,
+    fill_rule: FillRule
+}
 
-        let vertex_array = gl.create_vertex_array().unwrap();
-        gl.bind_vertex_array(Some(vertex_array));
-    
-        let vertex_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+impl PathShape {
+    pub unsafe fn new (gl: Arc<Context>, path: Path, fill_rule: FillRule, tolerance: impl Into<Tolerance>) -> Result<Self, TessellationError> {
+        let tolerance = tolerance.into();
+        let options = FillOptions::tolerance(tolerance.base()).with_fill_rule(fill_rule);
+        let mut mesh = Mesh::new(gl, &path, &options)?;
+        mesh.set_tolerance(tolerance)?;
 
-        let index_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
-    
-        gl.enable_vertex_attrib_array(0);
-        gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
-    
-        let mut vertices = Vec::new();
-        for vertex in geometry.vertices {
-            vertices.push(vertex.x,);
-            vertices.push(vertex.y);
-        }
-    
-        let mut vertex_buffer_data = Vec::<u8>::with_capacity(vertices.len() * 4);
-        for float in vertices.iter() {
-            vertex_buffer_data.extend_from_slice(&float.to_le_bytes());
-        }
+        Ok(Self {
+            mesh,
+            path,
+            fill_rule
+        })
+    }
 
-    
-        let mut index_buffer_data = Vec::<u8>::with_capacity(geometry.indices.len() * 2);
-        for n in geometry.indices.iter() {
-            index_buffer_data.extend_from_slice(&n.to_le_bytes());
-        }
-    
-        gl.buffer_data_u8_slice(
-            glow::ARRAY_BUFFER,
-            vertex_buffer_data.as_ref(),
-            glow::DYNAMIC_DRAW,
-        );
-
-        gl.buffer_data_u8_slice(
-            glow::ELEMENT_ARRAY_BUFFER,
-            index_buffer_data.as_ref(),
-            glow::DYNAMIC_DRAW,
-        );
+    pub fn set_path (&mut self, path: Path) -> Result<(), TessellationError> {
+        self.path = path;
+        self.retessellate()
+    }
+
+    pub fn set_fill_rule (&mut self, fill_rule: FillRule) -> Result<(), TessellationError> {
+        self.fill_rule = fill_rule;
+        self.retessellate()
+    }
+
+    pub fn set_tolerance (&mut self, tolerance: impl Into<Tolerance>) -> Result<(), TessellationError> {
+        self.mesh.set_tolerance(tolerance.into())
+    }
+
+    fn retessellate (&mut self) -> Result<(), TessellationError> {
+        self.mesh.set_path(&self.path, &FillOptions::default().with_fill_rule(self.fill_rule))
+    }
+
+    pub fn path (&self) -> &Path {
+        &self.path
+    }
+
+    pub fn fill_rule (&self) -> FillRule {
+        self.fill_rule
+    }
 
+    pub fn tolerance (&self) -> Tolerance {
+        self.mesh.tolerance()
+    }
+
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
+        self.mesh.draw_with(program, transform, color, projection)
+    }
+}
+
+impl GLObject for PathShape {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.mesh.render(program, uniforms)
+    }
+}
+
+impl Shape for PathShape {
+    fn path (&self) -> Path {
+        self.path.clone()
+    }
+
+    fn mesh (&self) -> &Mesh {
+        &self.mesh
+    }
+}
+
+/// The stroke of a path, tessellated with lyon's `StrokeTessellator`. Open paths give plain lines.
+pub struct Outline {
+    mesh: Mesh,
+    path: Path,
+    options: StrokeOptions
+}
+
+impl Outline {
+    /// Starts with the tolerance of `options`, later changes to it go through `set_tolerance`.
+    pub unsafe fn new (gl: Arc<Context>, path: Path, options: StrokeOptions) -> Result<Self, TessellationError> {
         Ok(Self {
-            vertex_array,
-            vertex_buffer,
-            index_buffer,
-            indices: geometry.indices.len(),
-            radius,
-            gl
+            mesh: Mesh::new_stroke(gl, &path, &options)?,
+            path,
+            options
         })
     }
 
-    pub fn draw_with(&self, program: u32, position: cgmath::Vector2<f32>, color: ColorUniforms, resolution: (u32, u32)) {
+    pub fn set_path (&mut self, path: Path) -> Result<(), TessellationError> {
+        self.path = path;
+        self.mesh.set_stroke_path(&self.path, &self.options)
+    }
+
+    /// The tolerance in `options` is ignored, see `set_tolerance`.
+    pub fn set_options (&mut self, options: StrokeOptions) -> Result<(), TessellationError> {
+        self.options = options;
+        self.mesh.set_stroke_path(&self.path, &self.options)
+    }
+
+    pub fn set_line_width (&mut self, width: f32) -> Result<(), TessellationError> {
+        self.set_options(self.options.with_line_width(width))
+    }
+
+    pub fn tolerance (&self) -> Tolerance {
+        self.mesh.tolerance()
+    }
+
+    pub fn set_tolerance (&mut self, tolerance: impl Into<Tolerance>) -> Result<(), TessellationError> {
+        self.mesh.set_tolerance(tolerance.into())
+    }
+
+    pub fn path (&self) -> &Path {
+        &self.path
+    }
+
+    pub fn options (&self) -> &StrokeOptions {
+        &self.options
+    }
+
+    pub fn mesh (&self) -> &Mesh {
+        &self.mesh
+    }
+
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
+        self.mesh.draw_with(program, transform, color, projection)
+    }
+
+    pub fn draw_instanced(&self, program: &Program, instances: &[Instance], projection: impl Into<ProjectionUniforms>) {
+        self.mesh.draw_instanced(program, instances, projection)
+    }
+
+    pub fn fill_with(&self, program: &Program, transform: impl Into<Transform2D>, paint: &Paint, projection: impl Into<ProjectionUniforms>) {
+        self.mesh.fill_with(program, transform, paint, projection)
+    }
+}
+
+impl GLObject for Outline {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.mesh.render(program, uniforms)
+    }
+}
+
+pub trait Shape {
+    fn path (&self) -> Path;
+    fn mesh (&self) -> &Mesh;
+
+    unsafe fn outline (&self, options: StrokeOptions) -> Result<Outline, TessellationError> {
+        Outline::new(self.mesh().gl().clone(), self.path(), options)
+    }
+
+    fn blend_mode (&self) -> BlendMode {
+        self.mesh().blend_mode()
+    }
+
+    fn set_blend_mode (&self, blend_mode: BlendMode) {
+        self.mesh().set_blend_mode(blend_mode)
+    }
+
+    fn tolerance (&self) -> Tolerance {
+        self.mesh().tolerance()
+    }
+
+    fn antialiasing (&self) -> Antialiasing {
+        self.mesh().antialiasing()
+    }
+
+    /// Kept when the shape is resized or its path changes.
+    unsafe fn set_antialiasing (&self, antialiasing: Antialiasing) -> Result<(), TessellationError> {
+        self.mesh().set_antialiasing(&self.path(), antialiasing)
+    }
+
+    fn draw_instanced (&self, program: &Program, instances: &[Instance], projection: impl Into<ProjectionUniforms>) where Self: Sized {
+        self.mesh().draw_instanced(program, instances, projection)
+    }
+
+    /// Fills the shape with a solid color or gradient, meant for the `paint` program of the `ShaderLibrary`.
+    fn fill_with (&self, program: &Program, transform: impl Into<Transform2D>, paint: &Paint, projection: impl Into<ProjectionUniforms>) where Self: Sized {
+        self.mesh().fill_with(program, transform, paint, projection)
+    }
+}
+
+/// Maps a texture onto a width x height quad. With a source rectangle only that part of the
+/// texture (in texels) is shown, for sprite sheets. Draw it with the `textured` program.
+pub struct Sprite {
+    mesh: Mesh,
+    texture: Arc<Texture>,
+    source: Option<[f32; 4]>,
+    flip_vertical: bool,
+    width: f32,
+    height: f32
+}
+
+impl Sprite {
+    pub unsafe fn new (gl: Arc<Context>, texture: Arc<Texture>, width: f32, height: f32) -> Self {
+        // a plain quad, no tolerance to choose
+        Self {
+            mesh: fill_mesh(gl, &rectangle_path(width, height, CornerType::Hard), Tolerance::default()).unwrap(),
+            texture,
+            source: None,
+            flip_vertical: false,
+            width,
+            height
+        }
+    }
+
+    pub fn update (&mut self, width: f32, height: f32) {
+        self.set_size(width, height);
+    }
+
+    pub fn width (&self) -> f32 {
+        self.width
+    }
+
+    pub fn height (&self) -> f32 {
+        self.height
+    }
+
+    /// The mesh follows on the next draw.
+    pub fn set_size (&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+        self.mesh.replace_path(self.path());
+    }
+
+    pub fn set_source (&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.source = Some([x, y, width, height]);
+    }
+
+    pub fn clear_source (&mut self) {
+        self.source = None;
+    }
+
+    pub fn source (&self) -> Option<[f32; 4]> {
+        self.source
+    }
+
+    pub fn set_flip_vertical (&mut self, flip_vertical: bool) {
+        self.flip_vertical = flip_vertical;
+    }
+
+    pub fn flip_vertical (&self) -> bool {
+        self.flip_vertical
+    }
+
+    pub fn set_texture (&mut self, texture: Arc<Texture>) {
+        self.texture = texture;
+    }
+
+    pub fn texture (&self) -> &Arc<Texture> {
+        &self.texture
+    }
+
+    // the source rectangle in uv space, (offset, size)
+    fn uv_rect (&self) -> [f32; 4] {
+        let [x, y, width, height] = match self.source {
+            Some([x, y, width, height]) => {
+                let (texture_width, texture_height) = (self.texture.width() as f32, self.texture.height() as f32);
+                [x / texture_width, y / texture_height, width / texture_width, height / texture_height]
+            },
+            None => [0., 0., 1., 1.]
+        };
+        if self.flip_vertical {
+            [x, y + height, width, -height]
+        } else {
+            [x, y, width, height]
+        }
+    }
+
+    /// `color` tints the texture, white leaves it untouched.
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
         let mut uniforms: Vec<Box<dyn Uniforms>> = Vec::new();
-        uniforms.push(Box::new(ProjectionUniforms::new(resolution)));
-        uniforms.push(Box::new({
-            let mut t = TransformUniforms::new();
-            t.translate(position.x, position.y);
-            t
-        }));
-        uniforms.push(Box::new(color));
-        uniforms.push(Box::new(GenericVec2Uniform::new(String::from("center"), position)));
-        uniforms.push(Box::new(GenericFloatUniform::new(String::from("range"), self.radius)));
+        uniforms.push(Box::new(projection.into()));
+        uniforms.push(Box::new(TransformUniforms::from(transform.into())));
+        uniforms.push(Box::new(color.into()));
+        uniforms.push(Box::new(TextureUniforms::new(0, cgmath::vec2(self.width, self.height), self.uv_rect())));
         unsafe { self.render(program, uniforms) }
     }
 }
 
-impl GLObject for RadialGradient {
-    unsafe fn render(&self, program: u32, uniforms: Vec<Box<dyn Uniforms>>) {
-        self.gl.use_program(Some(program));
-        self.gl.bind_vertex_array(Some(self.vertex_array));
-        self.gl.bind_buffer(ARRAY_BUFFER, Some(self.vertex_buffer));
-
-        self.gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(self.index_buffer));
-        for uniform in uniforms {
-            uniform.set_uniforms(&self.gl, program); // set up all the uniforms for our shader
+impl GLObject for Sprite {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.texture.bind(0);
+        self.mesh.render(program, uniforms)
+    }
+}
+
+impl Shape for Sprite {
+    fn path (&self) -> Path {
+        rectangle_path(self.width, self.height, CornerType::Hard)
+    }
+
+    fn mesh (&self) -> &Mesh {
+        &self.mesh
+    }
+}
+
+// a fill tessellated at the base of `tolerance`, which the mesh keeps for later changes
+unsafe fn fill_mesh (gl: Arc<Context>, path: &Path, tolerance: Tolerance) -> Result<Mesh, TessellationError> {
+    let mut mesh = Mesh::new(gl, path, &FillOptions::tolerance(tolerance.base()))?;
+    mesh.set_tolerance(tolerance)?;
+    Ok(mesh)
+}
+
+fn circle_path (radius: f32) -> Path {
+    let mut builder = Path::builder();
+    builder.add_circle(
+        point(0., 0.),
+        radius,
+        Winding::Positive
+    );
+    builder.build()
+}
+
+fn rectangle_path (width: f32, height: f32, kind: CornerType) -> Path {
+    let mut builder = Path::builder();
+    match kind {
+        CornerType::Hard => builder.add_rectangle(
+            &rect(0.0, 0.0, width, height),
+            Winding::Positive
+        ),
+        _ => {
+            let radii = kind.radii(width, height);
+            builder.add_rounded_rectangle(
+                &rect(0.0, 0.0, width, height),
+                &BorderRadii {
+                    top_left: radii.top_left,
+                    top_right: radii.top_right,
+                    bottom_left: radii.bottom_left,
+                    bottom_right: radii.bottom_right,
+                },
+                Winding::Positive
+            )
         }
-        self.gl.draw_elements(TRIANGLES, self.indices as i32, UNSIGNED_SHORT, 0);
     }
+    builder.build()
 }
 
 pub struct GenericVec2Uniform {
@@ -559,9 +770,8 @@ impl GenericVec2Uniform {
 }
 
 impl Uniforms for GenericVec2Uniform {
-    unsafe fn set_uniforms(&self, gl: &Context, program: u32) {
-        let location = gl.get_uniform_location(program, self.name.as_str()).unwrap();
-        gl.uniform_2_f32(Some(&location), self.value.x, self.value.y);
+    unsafe fn set_uniforms(&self, program: &Program) {
+        program.set_vec2(&self.name, self.value.x, self.value.y);
     }
 }
 
@@ -580,59 +790,96 @@ impl GenericFloatUniform {
 }
 
 impl Uniforms for GenericFloatUniform {
-    unsafe fn set_uniforms(&self, gl: &Context, program: u32) {
-        let location = gl.get_uniform_location(program, self.name.as_str()).unwrap();
-        gl.uniform_1_f32(Some(&location), self.value);
+    unsafe fn set_uniforms(&self, program: &Program) {
+        program.set_f32(&self.name, self.value);
     }
 }
 
-pub trait GLObject {
-    unsafe fn render(&self, program: u32, uniforms: Vec<Box<dyn Uniforms>>);
+/// Sampler unit and texture mapping for the `textured` program: `size` is the size of the mesh
+/// in pixels and `uv_rect` the part of the texture stretched over it, as (offset, size) in uv space.
+pub struct TextureUniforms {
+    unit: u32,
+    size: cgmath::Vector2<f32>,
+    uv_rect: [f32; 4]
 }
 
-pub unsafe fn set_clear_color (gl: &Context, color: ColorUniforms) {
-    gl.clear_color(color.color[0], color.color[1], color.color[2], 0.);
+impl TextureUniforms {
+    pub fn new (unit: u32, size: cgmath::Vector2<f32>, uv_rect: [f32; 4]) -> Self {
+        Self {
+            unit,
+            size,
+            uv_rect
+        }
+    }
 }
 
-pub fn compile_shader (gl: &glow::Context, vertex_shader_source: &str, fragment_shader_source: &str) -> u32 {
-    unsafe {
-        let program = gl.create_program().expect("Cannot create program"); // compile and link shader program
+impl Uniforms for TextureUniforms {
+    unsafe fn set_uniforms(&self, program: &Program) {
+        program.set_i32("tex", self.unit as i32);
+        program.set_vec2("size", self.size.x, self.size.y);
+        program.set_vec4("uv_rect", &self.uv_rect);
+    }
+}
 
-        let shader_sources = [
-            (glow::VERTEX_SHADER, vertex_shader_source),
-            (glow::FRAGMENT_SHADER, fragment_shader_source),
-        ];
-    
-        let mut shaders = Vec::with_capacity(shader_sources.len());
-    
-        for (shader_type, shader_source) in shader_sources.iter() {
-            let shader = gl
-                .create_shader(*shader_type)
-                .expect("Cannot create shader");
-            gl.shader_source(shader, &format!("{}\n{}", "#version 330", shader_source));
-            gl.compile_shader(shader);
-            if !gl.get_shader_compile_status(shader) {
-                std::panic::panic_any(gl.get_shader_info_log(shader));
-            }
-            gl.attach_shader(program, shader);
-            shaders.push(shader);
-        }
-    
-        gl.link_program(program);
-        if !gl.get_program_link_status(program) {
-            std::panic::panic_any(gl.get_program_info_log(program));
-        }
-    
-        for shader in shaders {
-            gl.detach_shader(program, shader);
-            gl.delete_shader(shader);
-        }
+pub trait GLObject {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>);
+}
 
-        program
-    }
+pub unsafe fn set_clear_color (gl: &Context, color: impl Into<ColorUniforms>) {
+    let [r, g, b, a] = color.into().color;
+    gl.clear_color(r, g, b, a);
 }
 
 pub struct OutlinedCircle {
-    pub outline: Circle,
-    pub inner: Circle
+    pub inner: Circle,
+    pub outline: Outline,
+    pub fill_color: ColorUniforms,
+    pub stroke_color: ColorUniforms
+}
+
+impl OutlinedCircle {
+    pub unsafe fn new (gl: Arc<Context>, radius: f32, stroke_width: f32, fill_color: impl Into<ColorUniforms>, stroke_color: impl Into<ColorUniforms>) -> Result<Self, TessellationError> {
+        let inner = Circle::new(gl, radius)?;
+        let outline = inner.outline(StrokeOptions::tolerance(DEFAULT_TOLERANCE).with_line_width(stroke_width))?;
+
+        Ok(Self {
+            inner,
+            outline,
+            fill_color: fill_color.into(),
+            stroke_color: stroke_color.into()
+        })
+    }
+
+    pub fn radius (&self) -> f32 {
+        self.inner.radius()
+    }
+
+    /// Resizes both the fill and the stroke on the next draw.
+    pub fn set_radius (&mut self, radius: f32) {
+        self.inner.set_radius(radius);
+        self.outline.path = circle_path(radius);
+        self.outline.mesh.replace_path(self.outline.path.clone());
+    }
+
+    pub fn stroke_width (&self) -> f32 {
+        self.outline.options().line_width
+    }
+
+    pub fn set_stroke_width (&mut self, width: f32) -> Result<(), TessellationError> {
+        self.outline.set_line_width(width)
+    }
+
+    /// Sets the tolerance of both the fill and the stroke.
+    pub fn set_tolerance (&mut self, tolerance: impl Into<Tolerance>) -> Result<(), TessellationError> {
+        let tolerance = tolerance.into();
+        self.inner.set_tolerance(tolerance)?;
+        self.outline.set_tolerance(tolerance)
+    }
+
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, projection: impl Into<ProjectionUniforms>) {
+        let transform = transform.into();
+        let projection = projection.into();
+        self.inner.draw_with(program, transform, self.fill_color, projection);
+        self.outline.draw_with(program, transform, self.stroke_color, projection);
+    }
 }
\ No newline at end of file
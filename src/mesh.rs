@@ -0,0 +1,609 @@
+use glow::*;
+use std::cell::{Cell, Ref, RefCell};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cgmath::InnerSpace;
+use lyon::math::Point;
+use lyon::path::Path;
+use lyon::tessellation::{FillRule, FillTessellator, FillOptions, StrokeTessellator, StrokeOptions, VertexBuffers, TessellationError};
+use lyon::tessellation::geometry_builder::simple_builder;
+
+use crate::antialias::{fringe, Fringe, FringeGeometry};
+use crate::resources::{GLBuffer, GLVertexArray, Handle};
+use crate::shader::ATTRIBUTE_LOCATIONS;
+use crate::{
+    Antialiasing, BlendMode, ColorUniforms, GLObject, Paint, Program, ProjectionUniforms, Transform2D, TransformUniforms, Uniforms,
+    ATTRIBUTE_COVERAGE, ATTRIBUTE_INSTANCE_COLOR, ATTRIBUTE_INSTANCE_OFFSET, ATTRIBUTE_INSTANCE_SCALE, ATTRIBUTE_POSITION
+};
+
+pub type Geometry = VertexBuffers<Point, u16>;
+
+const FLOATS_PER_INSTANCE: usize = 8; // offset, scale, color
+
+/// The tolerance shapes are built with unless given one, and the one `Tolerance::Auto` keeps
+/// the mesh's own geometry at.
+pub const DEFAULT_TOLERANCE: f32 = 0.1;
+
+/// The smallest tolerance a mesh is tessellated with, also the finest level of detail of
+/// `Tolerance::Auto`. Tessellating with a smaller one, a negative one or NaN fails with
+/// `TessellationError::UnsupportedParamater`.
+pub const MIN_TOLERANCE: f32 = 1. / 65536.;
+
+/// How closely the triangles of a mesh follow the curves of its path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+    /// The largest distance between the curves and the triangles, in the path's own units.
+    Fixed(f32),
+    /// Keeps the curves within `pixels` of the path on screen, whatever the transform or camera
+    /// zoom. `pixels` has to be positive and finite. Each draw picks a level of detail from the
+    /// scale it's drawn at and the viewport size of the projection, tolerances being powers of
+    /// two, tessellated the first time it's needed and cached until the path changes.
+    /// Instanced draws, batches and clips use the default fixed tolerance.
+    Auto {
+        pixels: f32
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::Fixed(DEFAULT_TOLERANCE)
+    }
+}
+
+impl Tolerance {
+    // the tolerance of the mesh's own geometry, the default one in automatic mode
+    pub(crate) fn base (&self) -> f32 {
+        match *self {
+            Tolerance::Fixed(tolerance) => tolerance,
+            Tolerance::Auto { .. } => DEFAULT_TOLERANCE
+        }
+    }
+
+    fn check (&self) -> Result<(), TessellationError> {
+        match *self {
+            Tolerance::Fixed(tolerance) => check_tolerance(tolerance),
+            Tolerance::Auto { pixels } if pixels > 0. && pixels.is_finite() => Ok(()),
+            Tolerance::Auto { .. } => Err(TessellationError::UnsupportedParamater)
+        }
+    }
+}
+
+// lyon asserts on tolerances that are too small, NaN fails the comparison too
+pub(crate) fn check_tolerance (tolerance: f32) -> Result<(), TessellationError> {
+    if tolerance >= MIN_TOLERANCE && tolerance.is_finite() {
+        Ok(())
+    } else {
+        Err(TessellationError::UnsupportedParamater)
+    }
+}
+
+impl From<f32> for Tolerance {
+    fn from(tolerance: f32) -> Self {
+        Tolerance::Fixed(tolerance)
+    }
+}
+
+// what the mesh was tessellated from, to do it again at another tolerance
+#[derive(Debug, Clone)]
+enum Source {
+    Fill(Path, FillOptions),
+    Stroke(Path, StrokeOptions)
+}
+
+/// Per-instance attributes for `Mesh::draw_instanced`, read by the `instanced` program of the `ShaderLibrary`.
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    pub position: cgmath::Vector2<f32>,
+    pub scale: cgmath::Vector2<f32>,
+    pub color: ColorUniforms
+}
+
+impl Instance {
+    pub fn new (position: cgmath::Vector2<f32>, scale: f32, color: impl Into<ColorUniforms>) -> Self {
+        Self {
+            position,
+            scale: cgmath::vec2(scale, scale),
+            color: color.into()
+        }
+    }
+}
+
+/// A tessellated lyon path living on the GPU. Every shape in the crate is a thin wrapper around one.
+#[derive(Debug)]
+pub struct Mesh {
+    vertex_array: VertexArray,
+    // created and set up by the first draw_instanced, most meshes are never drawn instanced
+    instance_buffer: RefCell<Option<Handle<GLBuffer>>>,
+    // bytes allocated for the instance buffer, grown on demand by draw_instanced
+    instance_capacity: Cell<usize>,
+    blend_mode: Cell<BlendMode>,
+    antialiasing: Cell<Antialiasing>,
+    fringe: RefCell<Option<Fringe>>,
+    source: RefCell<Option<Source>>,
+    tolerance: Tolerance,
+    // levels of detail in automatic mode, by log2 of their tolerance
+    lods: RefCell<HashMap<i32, Mesh>>,
+    geometry: RefCell<Geometry>,
+    // a path from replace_path, tessellated when the mesh is next used
+    pending: RefCell<Option<Path>>,
+    // the buffers and the fringe are behind the geometry
+    dirty: Cell<bool>,
+    // bytes allocated for the vertex and index buffers
+    buffer_capacity: Cell<(usize, usize)>,
+    usage: u32,
+    gl: Arc<Context>
+}
+
+impl Clone for Mesh {
+    // a clone gets its own GL objects, sharing the names would delete them twice
+    fn clone(&self) -> Self {
+        let mut mesh = unsafe { Self::from_geometry(self.gl.clone(), self.geometry().clone(), self.usage) };
+        mesh.source = self.source.clone();
+        mesh.tolerance = self.tolerance;
+        mesh.dirty.set(self.dirty.get());
+        mesh.set_blend_mode(self.blend_mode());
+        mesh.antialiasing.set(self.antialiasing());
+        if let Some(fringe) = self.fringe.borrow().as_ref() {
+            unsafe { mesh.set_fringe(Some(fringe.geometry().clone())) };
+        }
+        mesh
+    }
+}
+
+impl Mesh {
+    /// The mesh's tolerance starts as `Fixed(options.tolerance)`.
+    pub unsafe fn new (gl: Arc<Context>, path: &Path, options: &FillOptions) -> Result<Self, TessellationError> {
+        let mut mesh = Self::from_geometry(gl, Self::fill(path, options)?, glow::STATIC_DRAW);
+        mesh.source = RefCell::new(Some(Source::Fill(path.clone(), *options)));
+        mesh.tolerance = Tolerance::Fixed(options.tolerance);
+        Ok(mesh)
+    }
+
+    pub fn fill (path: &Path, options: &FillOptions) -> Result<Geometry, TessellationError> {
+        check_tolerance(options.tolerance)?;
+        let mut geometry: Geometry = VertexBuffers::new();
+        let mut tessellator = FillTessellator::new();
+        tessellator.tessellate_path(path, options, &mut simple_builder(&mut geometry))?;
+        Ok(geometry)
+    }
+
+    pub unsafe fn new_stroke (gl: Arc<Context>, path: &Path, options: &StrokeOptions) -> Result<Self, TessellationError> {
+        let mut mesh = Self::from_geometry(gl, Self::stroke(path, options)?, glow::STATIC_DRAW);
+        mesh.source = RefCell::new(Some(Source::Stroke(path.clone(), *options)));
+        mesh.tolerance = Tolerance::Fixed(options.tolerance);
+        Ok(mesh)
+    }
+
+    pub fn stroke (path: &Path, options: &StrokeOptions) -> Result<Geometry, TessellationError> {
+        check_tolerance(options.tolerance)?;
+        let mut geometry: Geometry = VertexBuffers::new();
+        let mut tessellator = StrokeTessellator::new();
+        tessellator.tessellate_path(path, options, &mut simple_builder(&mut geometry))?;
+        Ok(geometry)
+    }
+
+    pub unsafe fn from_geometry (gl: Arc<Context>, geometry: Geometry, usage: u32) -> Self {
+        let vertex_array = VertexArray::new(&gl, &[(ATTRIBUTE_POSITION, 2)]);
+
+        let mesh = Self {
+            vertex_array,
+            instance_buffer: RefCell::new(None),
+            instance_capacity: Cell::new(0),
+            blend_mode: Cell::new(BlendMode::Normal),
+            antialiasing: Cell::new(Antialiasing::None),
+            fringe: RefCell::new(None),
+            source: RefCell::new(None),
+            tolerance: Tolerance::default(),
+            lods: RefCell::new(HashMap::new()),
+            geometry: RefCell::new(geometry),
+            pending: RefCell::new(None),
+            dirty: Cell::new(false),
+            buffer_capacity: Cell::new((0, 0)),
+            usage,
+            gl
+        };
+        mesh.upload();
+        mesh
+    }
+
+    /// Tessellates the path right away, the buffers and the antialiasing fringe follow on the
+    /// next draw. The mesh keeps its own tolerance, the one in `options` is ignored, see `set_tolerance`.
+    pub fn set_path (&mut self, path: &Path, options: &FillOptions) -> Result<(), TessellationError> {
+        self.retessellate(Source::Fill(path.clone(), *options))
+    }
+
+    /// Swaps the path for one filled or stroked with the same options, tessellated and uploaded
+    /// the next time the mesh is used, so several changes in a frame only cost one. A path that
+    /// fails to tessellate leaves the previous one in place. Does nothing on a mesh made from
+    /// bare geometry.
+    pub fn replace_path (&self, path: Path) {
+        self.pending.replace(Some(path));
+    }
+
+    /// Drops the antialiasing fringe, which can only be rebuilt from a path, and the levels of
+    /// detail, `Tolerance::Auto` has no effect until a path is set again.
+    pub fn set_geometry (&mut self, geometry: Geometry) {
+        self.source.replace(None);
+        self.fringe.replace(None);
+        self.replace_geometry(geometry);
+    }
+
+    fn replace_geometry (&self, geometry: Geometry) {
+        self.geometry.replace(geometry);
+        self.pending.replace(None);
+        self.lods.borrow_mut().clear();
+        self.dirty.set(true);
+    }
+
+    fn retessellate (&mut self, source: Source) -> Result<(), TessellationError> {
+        self.replace_geometry(Self::tessellate(&source, self.base_tolerance())?);
+        self.source.replace(Some(source));
+        Ok(())
+    }
+
+    fn tessellate (source: &Source, tolerance: f32) -> Result<Geometry, TessellationError> {
+        match source {
+            Source::Fill(path, options) => Self::fill(path, &options.with_tolerance(tolerance)),
+            Source::Stroke(path, options) => Self::stroke(path, &options.with_tolerance(tolerance))
+        }
+    }
+
+    // CPU side of the pending path, so `geometry` is never stale
+    fn tessellate_pending (&self) {
+        let path = match self.pending.take() {
+            Some(path) => path,
+            None => return
+        };
+        let source = match self.source.borrow().as_ref() {
+            Some(Source::Fill(_, options)) => Source::Fill(path, *options),
+            Some(Source::Stroke(_, options)) => Source::Stroke(path, *options),
+            None => return
+        };
+        if let Ok(geometry) = Self::tessellate(&source, self.base_tolerance()) {
+            self.replace_geometry(geometry);
+            self.source.replace(Some(source));
+        }
+    }
+
+    // brings the buffers and the fringe up to date before drawing
+    unsafe fn sync (&self) {
+        self.tessellate_pending();
+        if !self.dirty.replace(false) {
+            return;
+        }
+        self.upload();
+        if let Some(Source::Fill(path, _)) = self.source.borrow().as_ref() {
+            if self.set_antialiasing(path, self.antialiasing()).is_err() {
+                self.set_fringe(None);
+            }
+        }
+    }
+
+    pub fn tolerance (&self) -> Tolerance {
+        self.tolerance
+    }
+
+    /// Tessellates the path again if the fixed tolerance changes. A mesh made from bare geometry
+    /// keeps it as it is. An invalid tolerance, see `MIN_TOLERANCE`, leaves the mesh unchanged.
+    pub fn set_tolerance (&mut self, tolerance: Tolerance) -> Result<(), TessellationError> {
+        tolerance.check()?;
+        self.tessellate_pending();
+        let previous = self.base_tolerance();
+        self.tolerance = tolerance;
+        self.lods.borrow_mut().clear();
+        match self.source.get_mut().clone() {
+            Some(source) if self.base_tolerance() != previous => self.retessellate(source),
+            _ => Ok(())
+        }
+    }
+
+    fn base_tolerance (&self) -> f32 {
+        self.tolerance.base()
+    }
+
+    pub fn antialiasing (&self) -> Antialiasing {
+        self.antialiasing.get()
+    }
+
+    /// `path` has to be the one the mesh was filled from. Stroke meshes have no fringe.
+    pub unsafe fn set_antialiasing (&self, path: &Path, antialiasing: Antialiasing) -> Result<(), TessellationError> {
+        self.antialiasing.set(antialiasing);
+        self.lods.borrow_mut().clear();
+        match antialiasing {
+            Antialiasing::None => self.set_fringe(None),
+            Antialiasing::Fringe { width } => self.set_fringe(Some(fringe(path, width, self.base_tolerance(), self.fill_rule())?))
+        }
+        Ok(())
+    }
+
+    fn fill_rule (&self) -> FillRule {
+        match self.source.borrow().as_ref() {
+            Some(Source::Fill(_, options)) => options.fill_rule,
+            _ => FillOptions::DEFAULT_FILL_RULE
+        }
+    }
+
+    unsafe fn set_fringe (&self, geometry: Option<FringeGeometry>) {
+        self.fringe.replace(geometry.map(|geometry| Fringe::new(self.gl.clone(), geometry)));
+    }
+
+    /// Like `set_path`, the tolerance in `options` is ignored.
+    pub fn set_stroke_path (&mut self, path: &Path, options: &StrokeOptions) -> Result<(), TessellationError> {
+        self.retessellate(Source::Stroke(path.clone(), *options))
+    }
+
+    pub fn blend_mode (&self) -> BlendMode {
+        self.blend_mode.get()
+    }
+
+    pub fn set_blend_mode (&self, blend_mode: BlendMode) {
+        self.blend_mode.set(blend_mode);
+    }
+
+    pub fn gl (&self) -> &Arc<Context> {
+        &self.gl
+    }
+
+    pub fn geometry (&self) -> Ref<'_, Geometry> {
+        self.tessellate_pending();
+        self.geometry.borrow()
+    }
+
+    pub fn indices (&self) -> usize {
+        self.geometry().indices.len()
+    }
+
+    unsafe fn upload (&self) {
+        let geometry = self.geometry.borrow();
+        self.vertex_array.bind(&self.gl);
+
+        let vertex_buffer_data = float_bytes(geometry.vertices.iter().flat_map(|vertex| [vertex.x, vertex.y]));
+        let index_buffer_data = index_bytes(&geometry.indices);
+
+        let (vertex_capacity, index_capacity) = self.buffer_capacity.get();
+        self.buffer_capacity.set((
+            write_buffer(&self.gl, glow::ARRAY_BUFFER, &vertex_buffer_data, vertex_capacity, self.usage),
+            write_buffer(&self.gl, glow::ELEMENT_ARRAY_BUFFER, &index_buffer_data, index_capacity, self.usage)
+        ));
+    }
+
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
+        let (transform, projection) = (transform.into(), projection.into());
+        let mut uniforms: Vec<Box<dyn Uniforms>> = Vec::new();
+        uniforms.push(Box::new(projection));
+        uniforms.push(Box::new(TransformUniforms::from(transform)));
+        uniforms.push(Box::new(color.into()));
+        unsafe { self.render_lod(program, uniforms, transform, projection) }
+    }
+
+    pub fn fill_with(&self, program: &Program, transform: impl Into<Transform2D>, paint: &Paint, projection: impl Into<ProjectionUniforms>) {
+        let (transform, projection) = (transform.into(), projection.into());
+        let mut uniforms: Vec<Box<dyn Uniforms>> = Vec::new();
+        uniforms.push(Box::new(projection));
+        uniforms.push(Box::new(TransformUniforms::from(transform)));
+        uniforms.push(Box::new(paint.clone()));
+        unsafe { self.render_lod(program, uniforms, transform, projection) }
+    }
+
+    /// Renders the level of detail for the scale `transform` and `projection` draw at with
+    /// `Tolerance::Auto`, otherwise the mesh itself.
+    pub unsafe fn render_lod (&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>, transform: Transform2D, projection: ProjectionUniforms) {
+        self.with_lod(transform, projection, |mesh| mesh.render(program, uniforms))
+    }
+
+    // only the triangles, without the fringe, for stencil masks where any coverage counts as inside
+    pub(crate) unsafe fn render_fill (&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>, transform: Transform2D, projection: ProjectionUniforms) {
+        self.with_lod(transform, projection, |mesh| mesh.draw_triangles(program, uniforms))
+    }
+
+    unsafe fn with_lod<F: FnOnce(&Mesh)> (&self, transform: Transform2D, projection: ProjectionUniforms, draw: F) {
+        self.tessellate_pending();
+        let level = match self.lod_level(transform, projection) {
+            Some(level) => level,
+            None => return draw(self)
+        };
+        if !self.lods.borrow().contains_key(&level) {
+            match self.tessellate_lod(level) {
+                Ok(lod) => { self.lods.borrow_mut().insert(level, lod); },
+                // the path tessellated fine at the base tolerance, so fall back to that
+                Err(_) => return draw(self)
+            }
+        }
+        let lods = self.lods.borrow();
+        let lod = &lods[&level];
+        lod.set_blend_mode(self.blend_mode());
+        draw(lod)
+    }
+
+    // log2 of the tolerance that keeps the error within the requested pixels
+    fn lod_level (&self, transform: Transform2D, projection: ProjectionUniforms) -> Option<i32> {
+        let pixels = match self.tolerance {
+            Tolerance::Auto { pixels } => pixels,
+            Tolerance::Fixed(_) => return None
+        };
+        self.source.borrow().as_ref()?;
+
+        let (width, height) = projection.viewport()?;
+        let (width, height) = (width as f32 / 2., height as f32 / 2.);
+        // how many pixels one local unit covers, along the axis that's stretched the most
+        let matrix = projection.matrix() * transform.to_matrix4();
+        let scale = cgmath::vec2(matrix.x.x * width, matrix.x.y * height).magnitude()
+            .max(cgmath::vec2(matrix.y.x * width, matrix.y.y * height).magnitude());
+        if !scale.is_normal() {
+            return None;
+        }
+        Some((pixels / scale).log2().floor().clamp(MIN_TOLERANCE.log2(), 16.) as i32)
+    }
+
+    unsafe fn tessellate_lod (&self, level: i32) -> Result<Mesh, TessellationError> {
+        let tolerance = 2f32.powi(level);
+        let source = self.source.borrow();
+        let source = source.as_ref().unwrap();
+        let lod = Self::from_geometry(self.gl.clone(), Self::tessellate(source, tolerance)?, glow::STATIC_DRAW);
+        if let (Source::Fill(path, options), Antialiasing::Fringe { width }) = (source, self.antialiasing()) {
+            lod.set_fringe(Some(fringe(path, width, tolerance, options.fill_rule)?));
+        }
+        Ok(lod)
+    }
+
+    unsafe fn draw_triangles (&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.sync();
+        program.bind();
+        self.blend_mode().apply(&self.gl);
+        self.vertex_array.bind(&self.gl);
+        for uniform in uniforms {
+            uniform.set_uniforms(program); // set up all the uniforms for our shader
+        }
+        // the fill has no coverage array, every vertex gets this constant
+        self.gl.vertex_attrib_1_f32(ATTRIBUTE_COVERAGE, 1.);
+        program.set_i32("premultiplied", (self.blend_mode() == BlendMode::Premultiplied) as i32);
+        self.gl.draw_elements(TRIANGLES, self.indices() as i32, UNSIGNED_SHORT, 0);
+    }
+
+    // only done for meshes that are actually drawn instanced, GL 2.1 contexts don't have glVertexAttribDivisor
+    unsafe fn enable_instancing (&self) {
+        let stride = (FLOATS_PER_INSTANCE * 4) as i32;
+        for (location, size, offset) in [
+            (ATTRIBUTE_INSTANCE_OFFSET, 2, 0),
+            (ATTRIBUTE_INSTANCE_SCALE, 2, 8),
+            (ATTRIBUTE_INSTANCE_COLOR, 4, 16)
+        ].iter() {
+            self.gl.enable_vertex_attrib_array(*location);
+            self.gl.vertex_attrib_pointer_f32(*location, *size, glow::FLOAT, false, stride, *offset);
+            self.gl.vertex_attrib_divisor(*location, 1);
+        }
+    }
+
+    /// Draws the mesh once per instance with a single `draw_elements_instanced`; only the instances are uploaded.
+    /// Needs GL 3.3, ES 3.0 or WebGL2.
+    pub fn draw_instanced(&self, program: &Program, instances: &[Instance], projection: impl Into<ProjectionUniforms>) {
+        if instances.is_empty() {
+            return;
+        }
+
+        let instance_buffer_data = float_bytes(instances.iter().flat_map(|instance| [
+            instance.position.x, instance.position.y,
+            instance.scale.x, instance.scale.y,
+            instance.color.color[0], instance.color.color[1], instance.color.color[2], instance.color.color[3]
+        ]));
+
+        unsafe {
+            self.sync();
+            program.bind();
+            self.blend_mode().apply(&self.gl);
+            self.vertex_array.bind(&self.gl);
+            let mut instance_buffer = self.instance_buffer.borrow_mut();
+            let first = instance_buffer.is_none();
+            let instance_buffer = instance_buffer.get_or_insert_with(|| Handle::new(&self.gl).unwrap());
+            self.gl.bind_buffer(ARRAY_BUFFER, Some(instance_buffer.raw()));
+            if first || !self.vertex_array.is_object() {
+                self.enable_instancing();
+            }
+            self.instance_capacity.set(write_buffer(&self.gl, ARRAY_BUFFER, &instance_buffer_data, self.instance_capacity.get(), glow::STREAM_DRAW));
+
+            projection.into().set_uniforms(program);
+            TransformUniforms::new().set_uniforms(program);
+            self.gl.draw_elements_instanced(TRIANGLES, self.indices() as i32, UNSIGNED_SHORT, 0, instances.len() as i32);
+        }
+    }
+}
+
+impl GLObject for Mesh {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.draw_triangles(program, uniforms);
+        if let Some(fringe) = self.fringe.borrow().as_ref() {
+            fringe.draw();
+        }
+    }
+}
+
+/// A vertex array object with its vertex and index buffers, for interleaved f32 attributes given
+/// as (location, components). Contexts without vertex array objects get the same state set up
+/// again on every `bind` instead.
+#[derive(Debug)]
+pub(crate) struct VertexArray {
+    vertex_array: Option<Handle<GLVertexArray>>,
+    vertex_buffer: Handle<GLBuffer>,
+    index_buffer: Handle<GLBuffer>,
+    attributes: &'static [(u32, i32)]
+}
+
+impl VertexArray {
+    // left bound, like after `bind`
+    pub(crate) unsafe fn new (gl: &Arc<Context>, attributes: &'static [(u32, i32)]) -> Self {
+        let vertex_array = Self {
+            vertex_array: Handle::new(gl).ok(),
+            vertex_buffer: Handle::new(gl).unwrap(),
+            index_buffer: Handle::new(gl).unwrap(),
+            attributes
+        };
+        vertex_array.bind(gl);
+        if vertex_array.vertex_array.is_some() {
+            // recorded in the vertex array object once and for all
+            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(vertex_array.index_buffer.raw()));
+            point_attributes(gl, attributes, 0);
+        }
+        vertex_array
+    }
+
+    /// Binds the vertex array and its index buffer, and its vertex buffer to `ARRAY_BUFFER`.
+    pub(crate) unsafe fn bind (&self, gl: &Context) {
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vertex_buffer.raw()));
+        match self.vertex_array.as_ref() {
+            Some(vertex_array) => gl.bind_vertex_array(Some(vertex_array.raw())),
+            None => {
+                gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(self.index_buffer.raw()));
+                point_attributes(gl, self.attributes, 0);
+                // the attribute arrays are global state here, the ones left enabled by other
+                // vertex arrays would be read past their end, or hide a constant like `coverage`
+                for (_, location) in ATTRIBUTE_LOCATIONS.iter() {
+                    if !self.attributes.iter().any(|(used, _)| used == location) {
+                        gl.disable_vertex_attrib_array(*location);
+                    }
+                }
+            }
+        }
+    }
+
+    // whether attribute state changes outlive the next bind
+    pub(crate) fn is_object (&self) -> bool {
+        self.vertex_array.is_some()
+    }
+}
+
+// points the attributes at the bound vertex buffer, starting from `first_vertex`
+pub(crate) unsafe fn point_attributes (gl: &Context, attributes: &[(u32, i32)], first_vertex: usize) {
+    let stride: i32 = attributes.iter().map(|(_, components)| components * 4).sum();
+    let mut offset = first_vertex as i32 * stride;
+    for (location, components) in attributes.iter() {
+        gl.enable_vertex_attrib_array(*location);
+        gl.vertex_attrib_pointer_f32(*location, *components, glow::FLOAT, false, stride, offset);
+        offset += components * 4;
+    }
+}
+
+pub(crate) fn float_bytes<I: IntoIterator<Item = f32>> (floats: I) -> Vec<u8> {
+    floats.into_iter().flat_map(|float| float.to_le_bytes()).collect()
+}
+
+pub(crate) fn index_bytes (indices: &[u16]) -> Vec<u8> {
+    indices.iter().flat_map(|index| index.to_le_bytes()).collect()
+}
+
+// fills the bound buffer, reallocating it only when the data doesn't fit, and returns its capacity.
+// Buffers that aren't static grow to the next power of two, so they settle after a few frames
+pub(crate) unsafe fn write_buffer (gl: &Context, target: u32, data: &[u8], capacity: usize, usage: u32) -> usize {
+    if capacity > 0 && data.len() <= capacity {
+        gl.buffer_sub_data_u8_slice(target, 0, data);
+        capacity
+    } else if usage == glow::STATIC_DRAW {
+        gl.buffer_data_u8_slice(target, data, usage);
+        data.len()
+    } else {
+        let capacity = data.len().next_power_of_two();
+        gl.buffer_data_size(target, capacity as i32, usage);
+        gl.buffer_sub_data_u8_slice(target, 0, data);
+        capacity
+    }
+}
@@ -0,0 +1,163 @@
+use cgmath::Vector2;
+
+use crate::{Color, Program, Uniforms};
+
+/// The most stops a gradient can have, the size of the arrays in the paint shader.
+pub const MAX_COLOR_STOPS: usize = 16;
+
+/// What happens past the ends of a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpreadMode {
+    // keeps the color of the first or last stop
+    #[default]
+    Pad,
+    Repeat,
+    // repeats, going back and forth
+    Reflect
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorStop {
+    // position along the gradient, 0 to 1
+    pub offset: f32,
+    pub color: Color
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientKind {
+    Linear {
+        start: Vector2<f32>,
+        end: Vector2<f32>
+    },
+    Radial {
+        center: Vector2<f32>,
+        radius: f32
+    },
+    // sweeps clockwise around the center, starting at `angle` radians from the +x axis
+    Conic {
+        center: Vector2<f32>,
+        angle: f32
+    }
+}
+
+/// Gradient geometry is in the local coordinates of the shape it fills, so it follows the
+/// shape's transform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub spread: SpreadMode,
+    stops: Vec<ColorStop>
+}
+
+impl Gradient {
+    pub fn new (kind: GradientKind) -> Self {
+        Self {
+            kind,
+            spread: SpreadMode::Pad,
+            stops: Vec::new()
+        }
+    }
+
+    pub fn linear (start: Vector2<f32>, end: Vector2<f32>) -> Self {
+        Self::new(GradientKind::Linear { start, end })
+    }
+
+    pub fn radial (center: Vector2<f32>, radius: f32) -> Self {
+        Self::new(GradientKind::Radial { center, radius })
+    }
+
+    pub fn conic (center: Vector2<f32>, angle: f32) -> Self {
+        Self::new(GradientKind::Conic { center, angle })
+    }
+
+    /// Stops are kept sorted by offset, one added at the same offset as another goes after it,
+    /// which gives a hard edge. Panics past `MAX_COLOR_STOPS`.
+    pub fn with_stop (mut self, offset: f32, color: impl Into<Color>) -> Self {
+        self.add_stop(offset, color);
+        self
+    }
+
+    pub fn with_spread (mut self, spread: SpreadMode) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn add_stop (&mut self, offset: f32, color: impl Into<Color>) {
+        assert!(self.stops.len() < MAX_COLOR_STOPS, "a gradient has at most {} stops", MAX_COLOR_STOPS);
+        let offset = offset.clamp(0., 1.);
+        let index = self.stops.iter().position(|stop| stop.offset > offset).unwrap_or(self.stops.len());
+        self.stops.insert(index, ColorStop {
+            offset,
+            color: color.into()
+        });
+    }
+
+    pub fn stops (&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    pub fn clear_stops (&mut self) {
+        self.stops.clear();
+    }
+}
+
+/// How the `paint` program of the `ShaderLibrary` fills a shape, see `Shape::fill_with`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    Solid(Color),
+    Gradient(Gradient)
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Paint::Solid(color)
+    }
+}
+
+impl From<Gradient> for Paint {
+    fn from(gradient: Gradient) -> Self {
+        Paint::Gradient(gradient)
+    }
+}
+
+impl Uniforms for Paint {
+    unsafe fn set_uniforms(&self, program: &Program) {
+        match self {
+            Paint::Solid(color) => {
+                program.set_i32("paint_kind", 0);
+                program.set_i32("stop_count", 1);
+                program.set_f32_array("stop_offsets", &[0.]);
+                program.set_vec4_array("stop_colors", &[color.to_array()]);
+            },
+            Paint::Gradient(gradient) => {
+                let (kind, start, end, radius, angle) = match gradient.kind {
+                    GradientKind::Linear { start, end } => (1, start, end, 0., 0.),
+                    GradientKind::Radial { center, radius } => (2, center, center, radius, 0.),
+                    GradientKind::Conic { center, angle } => (3, center, center, 0., angle)
+                };
+                program.set_i32("paint_kind", kind);
+                program.set_i32("spread_mode", match gradient.spread {
+                    SpreadMode::Pad => 0,
+                    SpreadMode::Repeat => 1,
+                    SpreadMode::Reflect => 2
+                });
+                program.set_vec2("gradient_start", start.x, start.y);
+                program.set_vec2("gradient_end", end.x, end.y);
+                program.set_f32("gradient_radius", radius);
+                program.set_f32("gradient_angle", angle);
+
+                // no stops draws nothing
+                let transparent = [ColorStop {
+                    offset: 0.,
+                    color: Color::TRANSPARENT
+                }];
+                let stops = if gradient.stops.is_empty() { &transparent[..] } else { &gradient.stops[..] };
+                let offsets: Vec<f32> = stops.iter().map(|stop| stop.offset).collect();
+                let colors: Vec<[f32; 4]> = stops.iter().map(|stop| stop.color.to_array()).collect();
+                program.set_i32("stop_count", stops.len() as i32);
+                program.set_f32_array("stop_offsets", &offsets);
+                program.set_vec4_array("stop_colors", &colors);
+            }
+        }
+    }
+}
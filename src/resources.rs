@@ -0,0 +1,301 @@
+use glow::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use crate::GlslVersion;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceKind {
+    Buffer,
+    VertexArray,
+    Texture,
+    Program,
+    Framebuffer,
+    Renderbuffer
+}
+
+impl ResourceKind {
+    unsafe fn create (&self, gl: &Context) -> Result<u32, String> {
+        match self {
+            ResourceKind::Buffer => gl.create_buffer(),
+            ResourceKind::VertexArray => gl.create_vertex_array(),
+            ResourceKind::Texture => gl.create_texture(),
+            ResourceKind::Program => gl.create_program(),
+            ResourceKind::Framebuffer => gl.create_framebuffer(),
+            ResourceKind::Renderbuffer => gl.create_renderbuffer()
+        }
+    }
+
+    unsafe fn delete (&self, gl: &Context, name: u32) {
+        match self {
+            ResourceKind::Buffer => gl.delete_buffer(name),
+            ResourceKind::VertexArray => gl.delete_vertex_array(name),
+            ResourceKind::Texture => gl.delete_texture(name),
+            ResourceKind::Program => gl.delete_program(name),
+            ResourceKind::Framebuffer => gl.delete_framebuffer(name),
+            ResourceKind::Renderbuffer => gl.delete_renderbuffer(name)
+        }
+    }
+
+    fn name (&self) -> &'static str {
+        match self {
+            ResourceKind::Buffer => "buffer",
+            ResourceKind::VertexArray => "vertex array",
+            ResourceKind::Texture => "texture",
+            ResourceKind::Program => "program",
+            ResourceKind::Framebuffer => "framebuffer",
+            ResourceKind::Renderbuffer => "renderbuffer"
+        }
+    }
+}
+
+/// The type of GL object a `Handle` owns.
+pub trait GLResource {
+    const KIND: ResourceKind;
+}
+
+#[derive(Debug)]
+pub enum GLBuffer {}
+
+#[derive(Debug)]
+pub enum GLVertexArray {}
+
+#[derive(Debug)]
+pub enum GLTexture {}
+
+#[derive(Debug)]
+pub enum GLProgram {}
+
+#[derive(Debug)]
+pub enum GLFramebuffer {}
+
+#[derive(Debug)]
+pub enum GLRenderbuffer {}
+
+impl GLResource for GLBuffer {
+    const KIND: ResourceKind = ResourceKind::Buffer;
+}
+
+impl GLResource for GLVertexArray {
+    const KIND: ResourceKind = ResourceKind::VertexArray;
+}
+
+impl GLResource for GLTexture {
+    const KIND: ResourceKind = ResourceKind::Texture;
+}
+
+impl GLResource for GLProgram {
+    const KIND: ResourceKind = ResourceKind::Program;
+}
+
+impl GLResource for GLFramebuffer {
+    const KIND: ResourceKind = ResourceKind::Framebuffer;
+}
+
+impl GLResource for GLRenderbuffer {
+    const KIND: ResourceKind = ResourceKind::Renderbuffer;
+}
+
+/// Owns one GL object. Dropping it never calls GL, so it's fine on any thread and after the
+/// context is gone: the object is queued in the context's `ResourceRegistry` and deleted on the
+/// next `collect`. Handles can't be cloned, a copy of a resource needs an object of its own.
+pub struct Handle<T: GLResource> {
+    name: u32,
+    registry: Arc<ResourceRegistry>,
+    kind: PhantomData<fn() -> T>
+}
+
+impl<T: GLResource> Handle<T> {
+    /// Creates an object on `gl`. This runs on the GL thread, so the released objects of the
+    /// context are collected first. Vertex arrays fail on contexts older than GL 3.0, ES 3.0 and WebGL2.
+    pub unsafe fn new (gl: &Arc<Context>) -> Result<Self, String> {
+        let registry = ResourceRegistry::of(gl);
+        registry.collect(gl);
+        if T::KIND == ResourceKind::VertexArray && !registry.supports_vertex_arrays(gl) {
+            return Err("vertex array objects aren't supported by this context".to_string());
+        }
+        let name = T::KIND.create(gl)?;
+        Ok(Self::register(registry, name))
+    }
+
+    /// Takes ownership of an object created on `gl` some other way.
+    pub fn from_raw (gl: &Arc<Context>, name: u32) -> Self {
+        Self::register(ResourceRegistry::of(gl), name)
+    }
+
+    fn register (registry: Arc<ResourceRegistry>, name: u32) -> Self {
+        registry.state().live.insert((T::KIND, name));
+        Self {
+            name,
+            registry,
+            kind: PhantomData
+        }
+    }
+
+    pub fn raw (&self) -> u32 {
+        self.name
+    }
+}
+
+impl<T: GLResource> Drop for Handle<T> {
+    fn drop(&mut self) {
+        self.registry.release(T::KIND, self.name);
+    }
+}
+
+impl<T: GLResource> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Handle").field(&T::KIND).field(&self.name).finish()
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    live: HashSet<(ResourceKind, u32)>,
+    // dropped handles waiting for collect
+    released: Vec<(ResourceKind, u32)>,
+    // the context is gone, and its objects with it
+    destroyed: bool,
+    // asked the first time a vertex array is created
+    vertex_arrays: Option<bool>
+}
+
+/// The GL objects of one context. `App` collects after every frame; without it, call `collect`
+/// once a frame, although creating a `Handle` collects too.
+#[derive(Debug, Default)]
+pub struct ResourceRegistry {
+    state: Mutex<State>
+}
+
+// one registry per context. The weak reference tells a context from a later one allocated at the same address.
+static REGISTRIES: Mutex<Vec<(Weak<Context>, Arc<ResourceRegistry>)>> = Mutex::new(Vec::new());
+
+impl ResourceRegistry {
+    /// The registry of `gl`, created the first time it's asked for.
+    pub fn of (gl: &Arc<Context>) -> Arc<Self> {
+        let mut registries = lock(&REGISTRIES);
+        registries.retain(|(context, _)| context.strong_count() > 0);
+        if let Some((_, registry)) = registries.iter().find(|(context, _)| std::ptr::eq(context.as_ptr(), Arc::as_ptr(gl))) {
+            return registry.clone();
+        }
+
+        let registry = Arc::new(Self::default());
+        registries.push((Arc::downgrade(gl), registry.clone()));
+        registry
+    }
+
+    fn state (&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+
+    // core since GL 3.0, ES 3.0 and WebGL2. ES 2.0 and WebGL1 only have them through an extension,
+    // under names glow doesn't load
+    unsafe fn supports_vertex_arrays (&self, gl: &Context) -> bool {
+        if let Some(supported) = self.state().vertex_arrays {
+            return supported;
+        }
+        let version = GlslVersion::detect(gl);
+        let supported = if version.es { version.version >= 300 } else { version.version >= 130 };
+        self.state().vertex_arrays = Some(supported);
+        supported
+    }
+
+    fn release (&self, kind: ResourceKind, name: u32) {
+        let mut state = self.state();
+        if !state.destroyed {
+            state.released.push((kind, name));
+        }
+    }
+
+    /// Deletes the objects whose handles were dropped, with `gl` current on this thread.
+    /// Returns how many there were.
+    pub unsafe fn collect (&self, gl: &Context) -> usize {
+        let released = {
+            let mut state = self.state();
+            if state.destroyed {
+                return 0;
+            }
+            let released = std::mem::take(&mut state.released);
+            for resource in released.iter() {
+                state.live.remove(resource);
+            }
+            released
+        };
+        for (kind, name) in released.iter() {
+            kind.delete(gl, *name);
+        }
+        released.len()
+    }
+
+    /// Objects released and not collected yet.
+    pub fn pending (&self) -> usize {
+        self.state().released.len()
+    }
+
+    /// Objects whose handles are alive.
+    pub fn live (&self) -> usize {
+        let state = self.state();
+        state.live.len() - state.released.len()
+    }
+
+    /// To call right before destroying the context, while it's still current: collects one last
+    /// time and reports the objects still alive. Handles dropped after this are ignored.
+    pub unsafe fn destroy (gl: &Arc<Context>) -> LeakReport {
+        let registry = Self::of(gl);
+        registry.collect(gl);
+        lock(&REGISTRIES).retain(|(_, other)| !Arc::ptr_eq(other, &registry));
+
+        let mut state = registry.state();
+        state.destroyed = true;
+        let mut leaked: Vec<(ResourceKind, u32)> = state.live.drain().collect();
+        leaked.sort();
+        LeakReport {
+            leaked
+        }
+    }
+}
+
+// a handle panicking in a drop elsewhere doesn't leave the registry inconsistent, keep going
+fn lock<T> (mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The objects still alive when their context was destroyed, by kind and GL name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeakReport {
+    pub leaked: Vec<(ResourceKind, u32)>
+}
+
+impl LeakReport {
+    pub fn is_empty (&self) -> bool {
+        self.leaked.is_empty()
+    }
+
+    pub fn count (&self, kind: ResourceKind) -> usize {
+        self.leaked.iter().filter(|(leaked, _)| *leaked == kind).count()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} GL objects leaked", self.leaked.len())?;
+        let mut separator = ": ";
+        for kind in [
+            ResourceKind::Buffer,
+            ResourceKind::VertexArray,
+            ResourceKind::Texture,
+            ResourceKind::Program,
+            ResourceKind::Framebuffer,
+            ResourceKind::Renderbuffer
+        ].iter() {
+            let count = self.count(*kind);
+            if count > 0 {
+                write!(f, "{}{} {}{}", separator, count, kind.name(), if count == 1 { "" } else { "s" })?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
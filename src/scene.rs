@@ -0,0 +1,396 @@
+use crate::{
+    Circle, ColorUniforms, Outline, OutlinedCircle, PathShape, ProjectionUniforms, RadialGradient, Rectangle, ShaderLibrary, Sprite, Transform2D
+};
+#[cfg(feature = "text")]
+use crate::Text;
+
+/// What a `Drawable` needs to draw itself: the programs to pick from and the projection, from
+/// a resolution or a `Camera2D`.
+pub struct RenderContext<'a> {
+    pub shaders: &'a ShaderLibrary,
+    pub projection: ProjectionUniforms
+}
+
+impl<'a> RenderContext<'a> {
+    pub fn new (shaders: &'a ShaderLibrary, projection: impl Into<ProjectionUniforms>) -> Self {
+        Self {
+            shaders,
+            projection: projection.into()
+        }
+    }
+}
+
+/// Anything that can draw itself with one of the `ShaderLibrary` programs, so it can be put in a `Scene`.
+pub trait Drawable {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms);
+}
+
+impl Drawable for Circle {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.solid, transform, color, ctx.projection)
+    }
+}
+
+impl Drawable for Rectangle {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.solid, transform, color, ctx.projection)
+    }
+}
+
+impl Drawable for RadialGradient {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.radial_gradient, transform, color, ctx.projection)
+    }
+}
+
+impl Drawable for PathShape {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.solid, transform, color, ctx.projection)
+    }
+}
+
+impl Drawable for Outline {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.solid, transform, color, ctx.projection)
+    }
+}
+
+impl Drawable for Sprite {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.textured, transform, color, ctx.projection)
+    }
+}
+
+/// Uses its own fill and stroke colors, the node's color is ignored.
+impl Drawable for OutlinedCircle {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, _color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.solid, transform, ctx.projection)
+    }
+}
+
+#[cfg(feature = "text")]
+impl Drawable for Text {
+    fn draw (&self, ctx: &RenderContext, transform: Transform2D, color: ColorUniforms) {
+        self.draw_with(&ctx.shaders.text, transform, color, ctx.projection)
+    }
+}
+
+/// Identifies a node of a `Scene`. Ids of removed nodes never match a node added later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32
+}
+
+pub struct Node {
+    pub drawable: Option<Box<dyn Drawable>>,
+    // relative to the parent node
+    pub transform: Transform2D,
+    pub color: ColorUniforms,
+    // hiding a node hides its children too
+    pub visible: bool,
+    // higher is drawn later, equal z keeps the tree order with parents before children
+    pub z: i32,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Node {
+    /// An empty group node, only there to move its children together.
+    pub fn new () -> Self {
+        Self {
+            drawable: None,
+            transform: Transform2D::identity(),
+            color: ColorUniforms::new(1., 1., 1.),
+            visible: true,
+            z: 0,
+            parent: None,
+            children: Vec::new()
+        }
+    }
+
+    pub fn with_drawable<D: Drawable + 'static> (mut self, drawable: D) -> Self {
+        self.drawable = Some(Box::new(drawable));
+        self
+    }
+
+    pub fn with_transform (mut self, transform: impl Into<Transform2D>) -> Self {
+        self.transform = transform.into();
+        self
+    }
+
+    pub fn with_color (mut self, color: impl Into<ColorUniforms>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    pub fn with_visible (mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    pub fn with_z (mut self, z: i32) -> Self {
+        self.z = z;
+        self
+    }
+
+    pub fn parent (&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children (&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+struct Slot {
+    node: Option<Node>,
+    generation: u32
+}
+
+/// A tree of nodes whose transforms are relative to their parent.
+#[derive(Default)]
+pub struct Scene {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>
+}
+
+impl Scene {
+    pub fn new () -> Self {
+        Self::default()
+    }
+
+    /// Adds a node at the top level.
+    pub fn add (&mut self, node: Node) -> NodeId {
+        let id = self.insert(node);
+        self.roots.push(id);
+        id
+    }
+
+    /// Panics if `parent` isn't in the scene.
+    pub fn add_child (&mut self, parent: NodeId, mut node: Node) -> NodeId {
+        assert!(self.contains(parent), "parent node isn't in the scene");
+        node.parent = Some(parent);
+        let id = self.insert(node);
+        self.slots[parent.index].node.as_mut().unwrap().children.push(id);
+        id
+    }
+
+    fn insert (&mut self, mut node: Node) -> NodeId {
+        node.children.clear();
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.node = Some(node);
+                NodeId {
+                    index,
+                    generation: slot.generation
+                }
+            },
+            None => {
+                self.slots.push(Slot {
+                    node: Some(node),
+                    generation: 0
+                });
+                NodeId {
+                    index: self.slots.len() - 1,
+                    generation: 0
+                }
+            }
+        }
+    }
+
+    /// Removes a node along with all of its descendants.
+    pub fn remove (&mut self, id: NodeId) {
+        if !self.contains(id) {
+            return;
+        }
+        self.detach(id);
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.slots[id.index];
+            if let Some(node) = slot.node.take() {
+                stack.extend(node.children);
+            }
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free.push(id.index);
+        }
+    }
+
+    /// Moves a node under another parent, or to the top level with `None`. The local transform
+    /// is kept, so the node moves with its new parent. Returns false and changes nothing if that
+    /// would make the node its own ancestor.
+    pub fn set_parent (&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if !self.contains(id) || parent.is_some_and(|parent| !self.contains(parent)) {
+            return false;
+        }
+
+        let mut ancestor = parent;
+        while let Some(current) = ancestor {
+            if current == id {
+                return false;
+            }
+            ancestor = self.slots[current.index].node.as_ref().unwrap().parent;
+        }
+
+        self.detach(id);
+        self.slots[id.index].node.as_mut().unwrap().parent = parent;
+        match parent {
+            Some(parent) => self.slots[parent.index].node.as_mut().unwrap().children.push(id),
+            None => self.roots.push(id)
+        }
+        true
+    }
+
+    // unlinks the node from its parent or from the roots
+    fn detach (&mut self, id: NodeId) {
+        let siblings = match self.slots[id.index].node.as_ref().unwrap().parent {
+            Some(parent) => &mut self.slots[parent.index].node.as_mut().unwrap().children,
+            None => &mut self.roots
+        };
+        siblings.retain(|sibling| *sibling != id);
+    }
+
+    pub fn contains (&self, id: NodeId) -> bool {
+        self.slots.get(id.index).is_some_and(|slot| slot.generation == id.generation && slot.node.is_some())
+    }
+
+    pub fn get (&self, id: NodeId) -> Option<&Node> {
+        if self.contains(id) {
+            self.slots[id.index].node.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut (&mut self, id: NodeId) -> Option<&mut Node> {
+        if self.contains(id) {
+            self.slots[id.index].node.as_mut()
+        } else {
+            None
+        }
+    }
+
+    pub fn roots (&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn len (&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty (&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The node's transform combined with all of its ancestors'.
+    pub fn world_transform (&self, id: NodeId) -> Option<Transform2D> {
+        let mut node = self.get(id)?;
+        let mut transform = node.transform;
+        while let Some(parent) = node.parent {
+            node = self.slots[parent.index].node.as_ref().unwrap();
+            transform = node.transform * transform;
+        }
+        Some(transform)
+    }
+
+    /// Draws every visible node, sorted by z.
+    pub fn draw (&self, ctx: &RenderContext) {
+        let mut queue = Vec::new();
+        let mut stack: Vec<(NodeId, Transform2D)> = self.roots.iter().rev().map(|id| (*id, Transform2D::identity())).collect();
+        while let Some((id, parent)) = stack.pop() {
+            let node = self.slots[id.index].node.as_ref().unwrap();
+            if !node.visible {
+                continue;
+            }
+
+            let world = parent * node.transform;
+            if let Some(drawable) = &node.drawable {
+                queue.push((node.z, drawable, node.color, world));
+            }
+            stack.extend(node.children.iter().rev().map(|child| (*child, world)));
+        }
+
+        // stable, so equal z stays in tree order
+        queue.sort_by_key(|(z, ..)| *z);
+        for (_, drawable, color, world) in queue {
+            drawable.draw(ctx, world, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{InnerSpace, Vector2};
+
+    fn node (transform: Transform2D) -> Node {
+        Node::new().with_transform(transform)
+    }
+
+    #[test]
+    fn world_transform_composes_through_parents () {
+        let mut scene = Scene::new();
+        let root = scene.add(node(Transform2D::translation(10., 0.)));
+        let child = scene.add_child(root, node(Transform2D::scaling(2., 2.)));
+        let grandchild = scene.add_child(child, node(Transform2D::translation(1., 0.)));
+
+        let world = scene.world_transform(grandchild).unwrap();
+        // moved to (1, 0) by itself, scaled to (2, 0) by its parent, then moved to (12, 0)
+        assert!((world.transform_point(Vector2::new(0., 0.)) - Vector2::new(12., 0.)).magnitude() < 1e-5);
+        assert_eq!(world, Transform2D::translation(10., 0.) * Transform2D::scaling(2., 2.) * Transform2D::translation(1., 0.));
+
+        assert!(scene.set_parent(grandchild, None));
+        assert_eq!(scene.world_transform(grandchild), Some(Transform2D::translation(1., 0.)));
+    }
+
+    #[test]
+    fn set_parent_rejects_cycles () {
+        let mut scene = Scene::new();
+        let a = scene.add(Node::new());
+        let b = scene.add_child(a, Node::new());
+        let c = scene.add_child(b, Node::new());
+
+        assert!(!scene.set_parent(a, Some(a)));
+        assert!(!scene.set_parent(a, Some(c)));
+        assert_eq!(scene.roots(), &[a]);
+        assert_eq!(scene.get(c).unwrap().parent(), Some(b));
+
+        assert!(scene.set_parent(c, Some(a)));
+        assert_eq!(scene.get(a).unwrap().children(), &[b, c]);
+        assert!(scene.get(b).unwrap().children().is_empty());
+    }
+
+    #[test]
+    fn removed_ids_stay_invalid_after_their_slot_is_reused () {
+        let mut scene = Scene::new();
+        let parent = scene.add(Node::new());
+        let child = scene.add_child(parent, Node::new());
+        scene.remove(parent);
+        assert!(!scene.contains(parent));
+        assert!(!scene.contains(child));
+        assert!(scene.is_empty());
+
+        let reused = scene.add(node(Transform2D::translation(5., 0.)));
+        let other = scene.add(Node::new());
+        assert!(reused.index == parent.index || reused.index == child.index);
+        assert!(reused != parent && reused != child);
+        assert!(scene.get(parent).is_none() && scene.get(child).is_none());
+        assert!(scene.world_transform(parent).is_none());
+        assert!(!scene.set_parent(other, Some(parent)));
+
+        scene.remove(parent);
+        scene.remove(child);
+        assert_eq!(scene.len(), 2);
+        assert!(scene.contains(reused));
+        assert_eq!(scene.world_transform(reused), Some(Transform2D::translation(5., 0.)));
+    }
+}
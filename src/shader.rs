@@ -0,0 +1,587 @@
+use glow::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::resources::{GLProgram, Handle};
+
+pub const SOLID_VERTEX_SHADER: &str = include_str!("shaders/solid.vert");
+pub const SOLID_FRAGMENT_SHADER: &str = include_str!("shaders/solid.frag");
+pub const RADIAL_GRADIENT_VERTEX_SHADER: &str = include_str!("shaders/radial_gradient.vert");
+pub const RADIAL_GRADIENT_FRAGMENT_SHADER: &str = include_str!("shaders/radial_gradient.frag");
+pub const TEXTURED_VERTEX_SHADER: &str = include_str!("shaders/textured.vert");
+pub const TEXTURED_FRAGMENT_SHADER: &str = include_str!("shaders/textured.frag");
+pub const TEXT_VERTEX_SHADER: &str = include_str!("shaders/text.vert");
+pub const TEXT_FRAGMENT_SHADER: &str = include_str!("shaders/text.frag");
+pub const BATCH_VERTEX_SHADER: &str = include_str!("shaders/batch.vert");
+pub const BATCH_FRAGMENT_SHADER: &str = include_str!("shaders/batch.frag");
+pub const INSTANCED_VERTEX_SHADER: &str = include_str!("shaders/instanced.vert");
+pub const PAINT_VERTEX_SHADER: &str = include_str!("shaders/paint.vert");
+pub const PAINT_FRAGMENT_SHADER: &str = include_str!("shaders/paint.frag");
+
+// attributes are bound by name before linking, so shaders don't need `layout (location = ..)`,
+// which GLSL 1.20 and ES 1.00 don't have
+pub const ATTRIBUTE_POSITION: u32 = 0;
+pub const ATTRIBUTE_TEX_COORDS: u32 = 1;
+pub const ATTRIBUTE_COLOR: u32 = 2;
+pub const ATTRIBUTE_INSTANCE_OFFSET: u32 = 3;
+pub const ATTRIBUTE_INSTANCE_SCALE: u32 = 4;
+pub const ATTRIBUTE_INSTANCE_COLOR: u32 = 5;
+pub const ATTRIBUTE_COVERAGE: u32 = 6;
+
+pub(crate) const ATTRIBUTE_LOCATIONS: &[(&str, u32)] = &[
+    ("position", ATTRIBUTE_POSITION),
+    ("tex_coords", ATTRIBUTE_TEX_COORDS),
+    ("color", ATTRIBUTE_COLOR),
+    ("instance_offset", ATTRIBUTE_INSTANCE_OFFSET),
+    ("instance_scale", ATTRIBUTE_INSTANCE_SCALE),
+    ("instance_color", ATTRIBUTE_INSTANCE_COLOR),
+    ("coverage", ATTRIBUTE_COVERAGE),
+];
+
+/// The GLSL dialect shaders are compiled as. The bundled shaders are written in the legacy
+/// `attribute`/`varying`/`gl_FragColor` style, and the header maps that onto newer dialects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlslVersion {
+    pub version: u32,
+    pub es: bool
+}
+
+impl GlslVersion {
+    pub const GL_120: Self = Self { version: 120, es: false };
+    pub const GL_330: Self = Self { version: 330, es: false };
+    pub const ES_100: Self = Self { version: 100, es: true };
+    pub const ES_300: Self = Self { version: 300, es: true };
+
+    pub fn new (version: u32, es: bool) -> Self {
+        Self {
+            version,
+            es
+        }
+    }
+
+    /// Picks the dialect from the `GL_VERSION` string of the current context.
+    pub fn detect (gl: &Context) -> Self {
+        Self::from_version_string(&unsafe { gl.get_parameter_string(glow::VERSION) })
+    }
+
+    pub fn from_version_string (version: &str) -> Self {
+        // "WebGL 2.0 (OpenGL ES 3.0 Chromium)", "OpenGL ES 3.2 Mesa 21.0.3", "4.6 (Core Profile) Mesa 21.0.3"
+        if let Some(rest) = version.strip_prefix("WebGL") {
+            return match parse_major_minor(rest) {
+                Some((major, _)) if major >= 2 => Self::ES_300,
+                _ => Self::ES_100
+            };
+        }
+        if let Some(rest) = version.strip_prefix("OpenGL ES") {
+            return match parse_major_minor(rest) {
+                Some((major, minor)) if major >= 3 => Self::new(300 + minor * 10, true),
+                _ => Self::ES_100
+            };
+        }
+        match parse_major_minor(version) {
+            Some((2, 0)) => Self::new(110, false),
+            Some((2, _)) => Self::GL_120,
+            Some((3, 0)) => Self::new(130, false),
+            Some((3, 1)) => Self::new(140, false),
+            Some((3, 2)) => Self::new(150, false),
+            Some((major, minor)) if major >= 3 => Self::new(major * 100 + minor * 10, false),
+            _ => Self::GL_330
+        }
+    }
+
+    // `in`/`out` instead of `attribute`/`varying`
+    fn is_modern (&self) -> bool {
+        if self.es { self.version >= 300 } else { self.version >= 130 }
+    }
+
+    pub fn header (&self, stage: ShaderStage, source: &str) -> String {
+        // GLSL ES 1.00 is the one version spelled without the `es` suffix
+        let mut header = if self.es && self.version >= 300 {
+            format!("#version {} es\n", self.version)
+        } else {
+            format!("#version {}\n", self.version)
+        };
+
+        if self.es && stage == ShaderStage::Fragment {
+            header.push_str("#ifdef GL_FRAGMENT_PRECISION_HIGH\nprecision highp float;\n#else\nprecision mediump float;\n#endif\n");
+        }
+
+        if self.is_modern() {
+            match stage {
+                ShaderStage::Vertex => header.push_str("#define attribute in\n#define varying out\n"),
+                ShaderStage::Fragment => header.push_str("#define varying in\n")
+            }
+            header.push_str("#define texture2D texture\n");
+            // only declare an output for shaders written against gl_FragColor, a second output would break ones that declare their own
+            if stage == ShaderStage::Fragment && source.contains("gl_FragColor") {
+                header.push_str("out vec4 vsb_frag_color;\n#define gl_FragColor vsb_frag_color\n");
+            }
+        }
+
+        header
+    }
+}
+
+fn parse_major_minor (version: &str) -> Option<(u32, u32)> {
+    let version = version.trim_start();
+    let end = version.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(version.len());
+    let mut parts = version[..end].split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().and_then(|minor| minor.parse().ok()).unwrap_or(0);
+    Some((major, minor))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment
+}
+
+impl ShaderStage {
+    fn gl_type (&self) -> u32 {
+        match self {
+            ShaderStage::Vertex => glow::VERTEX_SHADER,
+            ShaderStage::Fragment => glow::FRAGMENT_SHADER
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment")
+        }
+    }
+}
+
+/// One line of a driver info log. `line` refers to the source the caller passed in, not to what the driver saw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    pub line: Option<usize>,
+    pub message: String
+}
+
+#[derive(Debug, Clone)]
+pub enum ShaderError {
+    CreateProgram(String),
+    CreateShader {
+        stage: ShaderStage,
+        message: String
+    },
+    Compile {
+        stage: ShaderStage,
+        log: String,
+        diagnostics: Vec<ShaderDiagnostic>
+    },
+    Link {
+        log: String
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::CreateProgram(message) => write!(f, "cannot create program: {}", message),
+            ShaderError::CreateShader { stage, message } => write!(f, "cannot create {} shader: {}", stage, message),
+            ShaderError::Compile { stage, log, .. } => write!(f, "{} shader failed to compile:\n{}", stage, log),
+            ShaderError::Link { log } => write!(f, "program failed to link:\n{}", log)
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// A linked GL program. Active uniforms and attributes are looked up once at link time, so setting
+/// a uniform is a hash lookup, and setting one the driver optimized out does nothing.
+#[derive(Debug)]
+pub struct Program {
+    program: Handle<GLProgram>,
+    uniforms: HashMap<String, UniformInfo>,
+    attributes: HashMap<String, AttributeInfo>,
+    gl: Arc<Context>
+}
+
+#[derive(Debug, Clone)]
+pub struct UniformInfo {
+    pub location: UniformLocation,
+    pub utype: u32,
+    pub size: i32
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AttributeInfo {
+    pub location: u32,
+    pub atype: u32,
+    pub size: i32
+}
+
+impl Program {
+    unsafe fn introspect (gl: Arc<Context>, handle: Handle<GLProgram>) -> Self {
+        let program = handle.raw();
+        let mut uniforms = HashMap::new();
+        for index in 0..gl.get_active_uniforms(program) {
+            let uniform = match gl.get_active_uniform(program, index) {
+                Some(uniform) => uniform,
+                None => continue
+            };
+            let location = match gl.get_uniform_location(program, &uniform.name) {
+                Some(location) => location,
+                None => continue // uniform block members have no location
+            };
+            let info = UniformInfo {
+                location,
+                utype: uniform.utype,
+                size: uniform.size
+            };
+            // arrays are reported as `name[0]`, make them reachable by their bare name too
+            if let Some(name) = uniform.name.strip_suffix("[0]") {
+                uniforms.insert(name.to_string(), info.clone());
+            }
+            uniforms.insert(uniform.name, info);
+        }
+
+        let mut attributes = HashMap::new();
+        for index in 0..gl.get_active_attributes(program) {
+            let attribute = match gl.get_active_attribute(program, index) {
+                Some(attribute) => attribute,
+                None => continue
+            };
+            if let Some(location) = gl.get_attrib_location(program, &attribute.name) {
+                attributes.insert(attribute.name, AttributeInfo {
+                    location,
+                    atype: attribute.atype,
+                    size: attribute.size
+                });
+            }
+        }
+
+        Self {
+            program: handle,
+            uniforms,
+            attributes,
+            gl
+        }
+    }
+
+    pub fn raw (&self) -> glow::Program {
+        self.program.raw()
+    }
+
+    pub fn gl (&self) -> &Arc<Context> {
+        &self.gl
+    }
+
+    pub fn uniform (&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+
+    pub fn attribute (&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes.get(name)
+    }
+
+    pub fn uniforms (&self) -> impl Iterator<Item = (&str, &UniformInfo)> {
+        self.uniforms.iter().map(|(name, info)| (name.as_str(), info))
+    }
+
+    pub fn attributes (&self) -> impl Iterator<Item = (&str, &AttributeInfo)> {
+        self.attributes.iter().map(|(name, info)| (name.as_str(), info))
+    }
+
+    pub unsafe fn bind (&self) {
+        self.gl.use_program(Some(self.program.raw()));
+    }
+
+    // the setters below act on the program currently in use, and do nothing for unknown names
+
+    pub unsafe fn set_f32 (&self, name: &str, x: f32) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            self.gl.uniform_1_f32(Some(&uniform.location), x);
+        }
+    }
+
+    pub unsafe fn set_i32 (&self, name: &str, x: i32) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            self.gl.uniform_1_i32(Some(&uniform.location), x);
+        }
+    }
+
+    pub unsafe fn set_vec2 (&self, name: &str, x: f32, y: f32) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            self.gl.uniform_2_f32(Some(&uniform.location), x, y);
+        }
+    }
+
+    pub unsafe fn set_vec3 (&self, name: &str, value: &[f32; 3]) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            self.gl.uniform_3_f32_slice(Some(&uniform.location), value);
+        }
+    }
+
+    pub unsafe fn set_vec4 (&self, name: &str, value: &[f32; 4]) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            self.gl.uniform_4_f32_slice(Some(&uniform.location), value);
+        }
+    }
+
+    pub unsafe fn set_f32_array (&self, name: &str, values: &[f32]) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            self.gl.uniform_1_f32_slice(Some(&uniform.location), values);
+        }
+    }
+
+    pub unsafe fn set_vec4_array (&self, name: &str, values: &[[f32; 4]]) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            let flat: Vec<f32> = values.iter().flatten().copied().collect();
+            self.gl.uniform_4_f32_slice(Some(&uniform.location), &flat);
+        }
+    }
+
+    pub unsafe fn set_mat4 (&self, name: &str, value: &[f32; 16]) {
+        if let Some(uniform) = self.uniforms.get(name) {
+            self.gl.uniform_matrix_4_f32_slice(Some(&uniform.location), false, value);
+        }
+    }
+}
+
+/// Compiles and links a program for the GLSL dialect of the current context, see `GlslVersion::detect`.
+pub fn compile_shader (gl: &Arc<Context>, vertex_shader_source: &str, fragment_shader_source: &str) -> Result<Program, ShaderError> {
+    compile_shader_with_version(gl, GlslVersion::detect(gl), vertex_shader_source, fragment_shader_source)
+}
+
+/// Sources that start with their own `#version` line are passed through untouched.
+pub fn compile_shader_with_version (gl: &Arc<Context>, version: GlslVersion, vertex_shader_source: &str, fragment_shader_source: &str) -> Result<Program, ShaderError> {
+    unsafe {
+        let handle = Handle::<GLProgram>::new(gl).map_err(ShaderError::CreateProgram)?; // compile and link shader program
+        let program = handle.raw();
+
+        let shader_sources = [
+            (ShaderStage::Vertex, vertex_shader_source),
+            (ShaderStage::Fragment, fragment_shader_source),
+        ];
+
+        let mut shaders = Vec::with_capacity(shader_sources.len());
+        let mut result = Ok(());
+
+        for (stage, shader_source) in shader_sources.iter() {
+            let shader = match gl.create_shader(stage.gl_type()) {
+                Ok(shader) => shader,
+                Err(message) => {
+                    result = Err(ShaderError::CreateShader { stage: *stage, message });
+                    break;
+                }
+            };
+            shaders.push(shader);
+            let header = if shader_source.trim_start().starts_with("#version") {
+                String::new()
+            } else {
+                version.header(*stage, shader_source)
+            };
+            gl.shader_source(shader, &format!("{}{}", header, shader_source));
+            gl.compile_shader(shader);
+            if !gl.get_shader_compile_status(shader) {
+                let (log, diagnostics) = remap_info_log(&gl.get_shader_info_log(shader), header.lines().count());
+                result = Err(ShaderError::Compile { stage: *stage, log, diagnostics });
+                break;
+            }
+            gl.attach_shader(program, shader);
+        }
+
+        if result.is_ok() {
+            for (name, location) in ATTRIBUTE_LOCATIONS.iter() {
+                gl.bind_attrib_location(program, *location, name);
+            }
+            gl.link_program(program);
+            if !gl.get_program_link_status(program) {
+                result = Err(ShaderError::Link { log: gl.get_program_info_log(program) });
+            }
+        }
+
+        for shader in shaders {
+            gl.detach_shader(program, shader);
+            gl.delete_shader(shader);
+        }
+
+        // on error the handle is dropped and the program deleted with it
+        result.map(|()| Program::introspect(gl.clone(), handle))
+    }
+}
+
+// drivers disagree on the log format, these are the common ones:
+//   mesa:        0:12(5): error: ...
+//   nvidia:      0(12) : error C0000: ...
+//   angle/intel: ERROR: 0:12: ...
+// the line number is rewritten to point into the caller's source, which doesn't contain our header
+fn remap_info_log (log: &str, offset: usize) -> (String, Vec<ShaderDiagnostic>) {
+    let mut remapped = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for line in log.lines().filter(|line| !line.trim().is_empty()) {
+        match find_line_number(line) {
+            Some((start, end, number)) => {
+                let number = number.saturating_sub(offset).max(1);
+                remapped.push(format!("{}{}{}", &line[..start], number, &line[end..]));
+                diagnostics.push(ShaderDiagnostic {
+                    line: Some(number),
+                    message: line[end..].trim_start_matches(|c: char| c == ')' || c == ':' || c.is_whitespace() || c.is_ascii_digit() || c == '(').to_string()
+                });
+            },
+            None => {
+                remapped.push(line.to_string());
+                diagnostics.push(ShaderDiagnostic {
+                    line: None,
+                    message: line.trim().to_string()
+                });
+            }
+        }
+    }
+
+    (remapped.join("\n"), diagnostics)
+}
+
+// returns the byte range of the line number and its value
+fn find_line_number (line: &str) -> Option<(usize, usize, usize)> {
+    let trimmed = line.trim_start();
+    let mut offset = line.len() - trimmed.len();
+    let mut rest = trimmed;
+    for prefix in ["ERROR:", "WARNING:"].iter() {
+        if let Some(stripped) = rest.strip_prefix(prefix) {
+            let stripped_ws = stripped.trim_start();
+            offset += rest.len() - stripped_ws.len();
+            rest = stripped_ws;
+            break;
+        }
+    }
+
+    // source string index
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 {
+        return None;
+    }
+    let separator = rest[digits..].chars().next()?;
+    if separator != ':' && separator != '(' {
+        return None;
+    }
+
+    let start = digits + 1;
+    let length = rest[start..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - start);
+    if length == 0 {
+        return None;
+    }
+    let number = rest[start..start + length].parse().ok()?;
+    Some((offset + start, offset + start + length, number))
+}
+
+/// The programs bundled with the crate, compiled once. Their uniform names and attribute
+/// locations match the `Uniforms` implementations and the vertex layout of `Mesh`.
+pub struct ShaderLibrary {
+    pub solid: Program,
+    pub radial_gradient: Program,
+    pub textured: Program,
+    pub text: Program,
+    pub batch: Program,
+    pub instanced: Program,
+    pub paint: Program,
+    version: GlslVersion
+}
+
+impl ShaderLibrary {
+    pub fn new (gl: &Arc<Context>) -> Result<Self, ShaderError> {
+        Self::with_version(gl, GlslVersion::detect(gl))
+    }
+
+    pub fn with_version (gl: &Arc<Context>, version: GlslVersion) -> Result<Self, ShaderError> {
+        Ok(Self {
+            solid: compile_shader_with_version(gl, version, SOLID_VERTEX_SHADER, SOLID_FRAGMENT_SHADER)?,
+            radial_gradient: compile_shader_with_version(gl, version, RADIAL_GRADIENT_VERTEX_SHADER, RADIAL_GRADIENT_FRAGMENT_SHADER)?,
+            textured: compile_shader_with_version(gl, version, TEXTURED_VERTEX_SHADER, TEXTURED_FRAGMENT_SHADER)?,
+            text: compile_shader_with_version(gl, version, TEXT_VERTEX_SHADER, TEXT_FRAGMENT_SHADER)?,
+            batch: compile_shader_with_version(gl, version, BATCH_VERTEX_SHADER, BATCH_FRAGMENT_SHADER)?,
+            // both only pass a per-vertex color through, so they share the fragment stage
+            instanced: compile_shader_with_version(gl, version, INSTANCED_VERTEX_SHADER, BATCH_FRAGMENT_SHADER)?,
+            paint: compile_shader_with_version(gl, version, PAINT_VERTEX_SHADER, PAINT_FRAGMENT_SHADER)?,
+            version
+        })
+    }
+
+    pub fn version (&self) -> GlslVersion {
+        self.version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_webgl () {
+        assert_eq!(GlslVersion::from_version_string("WebGL 1.0 (OpenGL ES 2.0 Chromium)"), GlslVersion::ES_100);
+        assert_eq!(GlslVersion::from_version_string("WebGL 2.0 (OpenGL ES 3.0 Chromium)"), GlslVersion::ES_300);
+    }
+
+    #[test]
+    fn detects_es () {
+        assert_eq!(GlslVersion::from_version_string("OpenGL ES 3.2 Mesa 21.0.3"), GlslVersion::new(320, true));
+        assert_eq!(GlslVersion::from_version_string("OpenGL ES 3.0 Mesa 21.0.3"), GlslVersion::ES_300);
+        assert_eq!(GlslVersion::from_version_string("OpenGL ES 2.0 Mesa 21.0.3"), GlslVersion::ES_100);
+    }
+
+    #[test]
+    fn detects_desktop () {
+        assert_eq!(GlslVersion::from_version_string("4.6 (Core Profile) Mesa 21.0.3"), GlslVersion::new(460, false));
+        assert_eq!(GlslVersion::from_version_string("3.3.0 NVIDIA 470.57.02"), GlslVersion::GL_330);
+        assert_eq!(GlslVersion::from_version_string("3.1 Mesa 21.0.3"), GlslVersion::new(140, false));
+        assert_eq!(GlslVersion::from_version_string("2.1"), GlslVersion::GL_120);
+        assert_eq!(GlslVersion::from_version_string("2.0"), GlslVersion::new(110, false));
+    }
+
+    #[test]
+    fn falls_back_to_330 () {
+        assert_eq!(GlslVersion::from_version_string(""), GlslVersion::GL_330);
+    }
+
+    #[test]
+    fn remaps_mesa_log () {
+        let (log, diagnostics) = remap_info_log("0:12(5): error: `color' undeclared\n", 4);
+        assert_eq!(log, "0:8(5): error: `color' undeclared");
+        assert_eq!(diagnostics, vec![ShaderDiagnostic {
+            line: Some(8),
+            message: "error: `color' undeclared".to_string()
+        }]);
+    }
+
+    #[test]
+    fn remaps_nvidia_log () {
+        let (log, diagnostics) = remap_info_log("0(12) : error C0000: syntax error", 4);
+        assert_eq!(log, "0(8) : error C0000: syntax error");
+        assert_eq!(diagnostics[0].line, Some(8));
+        assert_eq!(diagnostics[0].message, "error C0000: syntax error");
+    }
+
+    #[test]
+    fn remaps_angle_log () {
+        let (log, diagnostics) = remap_info_log("ERROR: 0:12: 'color' : undeclared identifier\nWARNING: 0:3: unused", 2);
+        assert_eq!(log, "ERROR: 0:10: 'color' : undeclared identifier\nWARNING: 0:1: unused");
+        assert_eq!(diagnostics[0].line, Some(10));
+        assert_eq!(diagnostics[0].message, "'color' : undeclared identifier");
+        assert_eq!(diagnostics[1].line, Some(1));
+    }
+
+    #[test]
+    fn lines_inside_the_header_point_at_the_first_line () {
+        let (log, diagnostics) = remap_info_log("0:2(1): error: in the header", 5);
+        assert_eq!(log, "0:1(1): error: in the header");
+        assert_eq!(diagnostics[0].line, Some(1));
+    }
+
+    #[test]
+    fn keeps_lines_without_a_number () {
+        let (log, diagnostics) = remap_info_log("\nlink failed\n\n", 3);
+        assert_eq!(log, "link failed");
+        assert_eq!(diagnostics, vec![ShaderDiagnostic {
+            line: None,
+            message: "link failed".to_string()
+        }]);
+    }
+}
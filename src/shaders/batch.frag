@@ -0,0 +1,5 @@
+varying vec4 vertex_color;
+
+void main() {
+    gl_FragColor = vertex_color;
+}
@@ -0,0 +1,11 @@
+attribute vec2 position;
+attribute vec4 color;
+
+uniform mat4 projection;
+
+varying vec4 vertex_color;
+
+void main() {
+    vertex_color = color;
+    gl_Position = projection * vec4(position, 0.0, 1.0);
+}
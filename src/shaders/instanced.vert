@@ -0,0 +1,14 @@
+attribute vec2 position;
+attribute vec2 instance_offset;
+attribute vec2 instance_scale;
+attribute vec4 instance_color;
+
+uniform mat4 projection;
+uniform mat4 transform;
+
+varying vec4 vertex_color;
+
+void main() {
+    vertex_color = instance_color;
+    gl_Position = projection * transform * vec4(position * instance_scale + instance_offset, 0.0, 1.0);
+}
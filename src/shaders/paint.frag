@@ -0,0 +1,61 @@
+#define MAX_STOPS 16
+
+// 0 solid, 1 linear, 2 radial, 3 conic
+uniform int paint_kind;
+// 0 pad, 1 repeat, 2 reflect
+uniform int spread_mode;
+// linear: from start to end, radial and conic: centered on start
+uniform vec2 gradient_start;
+uniform vec2 gradient_end;
+uniform float gradient_radius;
+uniform float gradient_angle;
+uniform int stop_count;
+uniform float stop_offsets[MAX_STOPS];
+uniform vec4 stop_colors[MAX_STOPS];
+// set under BlendMode::Premultiplied, where the coverage has to scale the color too
+uniform bool premultiplied;
+
+varying vec2 local_position;
+varying float fragment_coverage;
+
+float gradient_position() {
+    if (paint_kind == 1) {
+        vec2 direction = gradient_end - gradient_start;
+        return dot(local_position - gradient_start, direction) / max(dot(direction, direction), 0.000001);
+    } else if (paint_kind == 2) {
+        return distance(local_position, gradient_start) / max(gradient_radius, 0.000001);
+    } else if (paint_kind == 3) {
+        vec2 offset = local_position - gradient_start;
+        return fract((atan(offset.y, offset.x) - gradient_angle) / 6.28318531);
+    }
+    return 0.0;
+}
+
+float spread(float t) {
+    if (spread_mode == 1) {
+        return fract(t);
+    } else if (spread_mode == 2) {
+        return 1.0 - abs(mod(t, 2.0) - 1.0);
+    }
+    return clamp(t, 0.0, 1.0);
+}
+
+void main() {
+    float t = spread(gradient_position());
+    vec4 color = stop_colors[0];
+    for (int i = 1; i < MAX_STOPS; i++) {
+        if (i >= stop_count) {
+            break;
+        }
+        float previous = stop_offsets[i - 1];
+        if (t >= previous) {
+            float range = max(stop_offsets[i] - previous, 0.000001);
+            color = mix(stop_colors[i - 1], stop_colors[i], clamp((t - previous) / range, 0.0, 1.0));
+        }
+    }
+    gl_FragColor = color;
+    if (premultiplied) {
+        gl_FragColor.rgb *= fragment_coverage;
+    }
+    gl_FragColor.a *= fragment_coverage;
+}
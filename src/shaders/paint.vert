@@ -0,0 +1,15 @@
+attribute vec2 position;
+// 1 inside the shape, fading to 0 across an antialiasing fringe
+attribute float coverage;
+
+uniform mat4 projection;
+uniform mat4 transform;
+
+varying vec2 local_position;
+varying float fragment_coverage;
+
+void main() {
+    fragment_coverage = coverage;
+    local_position = position;
+    gl_Position = projection * transform * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,17 @@
+uniform vec4 ucolor;
+uniform vec2 center;
+uniform float range;
+// set under BlendMode::Premultiplied, where the coverage has to scale the color too
+uniform bool premultiplied;
+
+varying vec2 local_position;
+varying float fragment_coverage;
+
+void main() {
+    float t = clamp(distance(local_position, center) / range, 0.0, 1.0);
+    gl_FragColor = vec4(ucolor.rgb, ucolor.a * (1.0 - t));
+    if (premultiplied) {
+        gl_FragColor.rgb *= fragment_coverage;
+    }
+    gl_FragColor.a *= fragment_coverage;
+}
@@ -0,0 +1,13 @@
+uniform vec4 ucolor;
+// set under BlendMode::Premultiplied, where the coverage has to scale the color too
+uniform bool premultiplied;
+
+varying float fragment_coverage;
+
+void main() {
+    gl_FragColor = ucolor;
+    if (premultiplied) {
+        gl_FragColor.rgb *= fragment_coverage;
+    }
+    gl_FragColor.a *= fragment_coverage;
+}
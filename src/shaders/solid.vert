@@ -0,0 +1,13 @@
+attribute vec2 position;
+// 1 inside the shape, fading to 0 across an antialiasing fringe
+attribute float coverage;
+
+uniform mat4 projection;
+uniform mat4 transform;
+
+varying float fragment_coverage;
+
+void main() {
+    fragment_coverage = coverage;
+    gl_Position = projection * transform * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,8 @@
+uniform sampler2D atlas;
+uniform vec4 ucolor;
+
+varying vec2 uv;
+
+void main() {
+    gl_FragColor = vec4(ucolor.rgb, ucolor.a * texture2D(atlas, uv).r);
+}
@@ -0,0 +1,12 @@
+attribute vec2 position;
+attribute vec2 tex_coords;
+
+uniform mat4 projection;
+uniform mat4 transform;
+
+varying vec2 uv;
+
+void main() {
+    uv = tex_coords;
+    gl_Position = projection * transform * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+uniform sampler2D tex;
+uniform vec4 ucolor;
+// set under BlendMode::Premultiplied, where the coverage has to scale the color too
+uniform bool premultiplied;
+
+varying vec2 uv;
+varying float fragment_coverage;
+
+void main() {
+    gl_FragColor = texture2D(tex, uv) * ucolor;
+    if (premultiplied) {
+        gl_FragColor.rgb *= fragment_coverage;
+    }
+    gl_FragColor.a *= fragment_coverage;
+}
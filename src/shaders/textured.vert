@@ -0,0 +1,18 @@
+attribute vec2 position;
+// 1 inside the shape, fading to 0 across an antialiasing fringe
+attribute float coverage;
+
+uniform mat4 projection;
+uniform mat4 transform;
+// size of the mesh in pixels, and the sub-rectangle of the texture it maps to (offset, size) in uv space
+uniform vec2 size;
+uniform vec4 uv_rect;
+
+varying vec2 uv;
+varying float fragment_coverage;
+
+void main() {
+    fragment_coverage = coverage;
+    uv = uv_rect.xy + position / size * uv_rect.zw;
+    gl_Position = projection * transform * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,241 @@
+use glow::*;
+use std::cell::Cell;
+use std::sync::Arc;
+
+use crate::resources::{GLFramebuffer, GLRenderbuffer, Handle};
+use crate::{GLObject, Program, Sprite, Texture, Uniforms};
+
+/// An offscreen framebuffer with an RGBA color texture and an optional depth/stencil renderbuffer.
+/// While it's bound everything renders into the texture, which can then be drawn with a `Sprite`.
+/// A multisampled target renders into renderbuffers instead and resolves them into the texture
+/// on `unbind`, `resolve` or `read_pixels`.
+pub struct RenderTarget {
+    framebuffer: Handle<GLFramebuffer>,
+    texture: Arc<Texture>,
+    depth_stencil: Option<Handle<GLRenderbuffer>>,
+    // framebuffer and color renderbuffer that are drawn to when multisampled
+    multisample: Option<(Handle<GLFramebuffer>, Handle<GLRenderbuffer>)>,
+    samples: u32,
+    width: u32,
+    height: u32,
+    // framebuffer and viewport to go back to on unbind
+    previous: Cell<Option<(i32, [i32; 4])>>,
+    gl: Arc<Context>
+}
+
+impl RenderTarget {
+    pub unsafe fn new (gl: Arc<Context>, width: u32, height: u32, depth_stencil: bool) -> Result<Self, String> {
+        Self::new_multisampled(gl, width, height, 0, depth_stencil)
+    }
+
+    /// A target with `samples` samples per pixel, clamped to what the context supports.
+    /// 0 or 1 makes a plain target. Needs GL 3.0 or GLES 3.0.
+    pub unsafe fn new_multisampled (gl: Arc<Context>, width: u32, height: u32, samples: u32, depth_stencil: bool) -> Result<Self, String> {
+        let samples = if samples > 1 {
+            samples.min(gl.get_parameter_i32(glow::MAX_SAMPLES).max(0) as u32)
+        } else {
+            0
+        };
+        let multisample = if samples > 1 {
+            Some((Handle::new(&gl)?, Handle::new(&gl)?))
+        } else {
+            None
+        };
+        let samples = if multisample.is_some() { samples } else { 0 };
+        let framebuffer = Handle::new(&gl)?;
+        let depth_stencil = if depth_stencil {
+            Some(Handle::new(&gl)?)
+        } else {
+            None
+        };
+
+        let mut target = Self {
+            framebuffer,
+            texture: Arc::new(Texture::from_rgba(gl.clone(), width, height, None)),
+            depth_stencil,
+            multisample,
+            samples,
+            width,
+            height,
+            previous: Cell::new(None),
+            gl
+        };
+        target.attach(width, height)?;
+        Ok(target)
+    }
+
+    // (re)allocates the attachments at the given size
+    unsafe fn attach (&mut self, width: u32, height: u32) -> Result<(), String> {
+        if (width, height) != self.texture.size() {
+            self.texture = Arc::new(Texture::from_rgba(self.gl.clone(), width, height, None));
+        }
+
+        let previous = self.gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING);
+        self.gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer.raw()));
+        self.gl.framebuffer_texture_2d(glow::FRAMEBUFFER, glow::COLOR_ATTACHMENT0, glow::TEXTURE_2D, Some(self.texture.raw()), 0);
+        let mut status = self.gl.check_framebuffer_status(glow::FRAMEBUFFER);
+
+        // the depth/stencil buffer goes wherever the drawing happens
+        if let Some((framebuffer, color)) = &self.multisample {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer.raw()));
+            self.gl.bind_renderbuffer(glow::RENDERBUFFER, Some(color.raw()));
+            self.gl.renderbuffer_storage_multisample(glow::RENDERBUFFER, self.samples as i32, glow::RGBA8, width as i32, height as i32);
+            self.gl.framebuffer_renderbuffer(glow::FRAMEBUFFER, glow::COLOR_ATTACHMENT0, glow::RENDERBUFFER, Some(color.raw()));
+        }
+        if let Some(depth_stencil) = &self.depth_stencil {
+            self.gl.bind_renderbuffer(glow::RENDERBUFFER, Some(depth_stencil.raw()));
+            if self.multisample.is_some() {
+                self.gl.renderbuffer_storage_multisample(glow::RENDERBUFFER, self.samples as i32, glow::DEPTH24_STENCIL8, width as i32, height as i32);
+            } else {
+                self.gl.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH24_STENCIL8, width as i32, height as i32);
+            }
+            self.gl.framebuffer_renderbuffer(glow::FRAMEBUFFER, glow::DEPTH_STENCIL_ATTACHMENT, glow::RENDERBUFFER, Some(depth_stencil.raw()));
+        }
+        self.gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+
+        if status == glow::FRAMEBUFFER_COMPLETE {
+            status = self.gl.check_framebuffer_status(glow::FRAMEBUFFER);
+        }
+        self.gl.bind_framebuffer(glow::FRAMEBUFFER, framebuffer_from_binding(previous));
+
+        self.width = width;
+        self.height = height;
+        if status == glow::FRAMEBUFFER_COMPLETE {
+            Ok(())
+        } else {
+            Err(format!("framebuffer is incomplete: 0x{:x}", status))
+        }
+    }
+
+    /// Reallocates the attachments, the contents are lost. Sprites holding the old texture keep
+    /// showing it, get the new one from `texture`.
+    pub unsafe fn resize (&mut self, width: u32, height: u32) -> Result<(), String> {
+        if (width, height) == (self.width, self.height) {
+            return Ok(());
+        }
+        self.attach(width, height)
+    }
+
+    /// Binds the framebuffer and sets the viewport to cover it, remembering what was bound before.
+    pub unsafe fn bind (&self) {
+        let mut viewport = [0; 4];
+        self.gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+        self.previous.set(Some((self.gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING), viewport)));
+
+        self.gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.draw_framebuffer()));
+        self.gl.viewport(0, 0, self.width as i32, self.height as i32);
+    }
+
+    /// Restores the framebuffer and viewport that were current when `bind` was called,
+    /// resolving a multisampled target first.
+    pub unsafe fn unbind (&self) {
+        if let Some((framebuffer, [x, y, width, height])) = self.previous.take() {
+            self.resolve();
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, framebuffer_from_binding(framebuffer));
+            self.gl.viewport(x, y, width, height);
+        }
+    }
+
+    /// Copies the samples into the texture, averaging them. Does nothing for a plain target.
+    pub unsafe fn resolve (&self) {
+        if let Some((multisample, _)) = &self.multisample {
+            let read = self.gl.get_parameter_i32(glow::READ_FRAMEBUFFER_BINDING);
+            let draw = self.gl.get_parameter_i32(glow::DRAW_FRAMEBUFFER_BINDING);
+            let scissor = self.gl.is_enabled(glow::SCISSOR_TEST);
+            self.gl.disable(glow::SCISSOR_TEST);
+
+            self.gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(multisample.raw()));
+            self.gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.framebuffer.raw()));
+            let (width, height) = (self.width as i32, self.height as i32);
+            self.gl.blit_framebuffer(0, 0, width, height, 0, 0, width, height, glow::COLOR_BUFFER_BIT, glow::NEAREST);
+
+            self.gl.bind_framebuffer(glow::READ_FRAMEBUFFER, framebuffer_from_binding(read));
+            self.gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, framebuffer_from_binding(draw));
+            if scissor {
+                self.gl.enable(glow::SCISSOR_TEST);
+            }
+        }
+    }
+
+    /// Runs `draw` with the target bound.
+    pub unsafe fn draw<F: FnOnce()> (&self, draw: F) {
+        self.bind();
+        draw();
+        self.unbind();
+    }
+
+    pub unsafe fn render (&self, object: &dyn GLObject, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        self.draw(|| object.render(program, uniforms));
+    }
+
+    /// A sprite showing the whole target. GL stores the bottom row first, so it's flipped vertically.
+    pub unsafe fn sprite (&self) -> Sprite {
+        let mut sprite = Sprite::new(self.gl.clone(), self.texture.clone(), self.width as f32, self.height as f32);
+        sprite.set_flip_vertical(true);
+        sprite
+    }
+
+    /// Reads the target back as tightly packed RGBA, top row first like an image file.
+    pub unsafe fn read_pixels (&self) -> Vec<u8> {
+        self.resolve();
+        let previous = self.gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING);
+        self.gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer.raw()));
+        let pixels = read_pixels(&self.gl, 0, 0, self.width, self.height);
+        self.gl.bind_framebuffer(glow::FRAMEBUFFER, framebuffer_from_binding(previous));
+        pixels
+    }
+
+    pub fn texture (&self) -> &Arc<Texture> {
+        &self.texture
+    }
+
+    /// The framebuffer holding the texture. When multisampled, `bind` draws to another one.
+    pub fn raw (&self) -> u32 {
+        self.framebuffer.raw()
+    }
+
+    // where draw calls go while bound
+    fn draw_framebuffer (&self) -> u32 {
+        self.multisample.as_ref().map_or(&self.framebuffer, |(framebuffer, _)| framebuffer).raw()
+    }
+
+    /// Samples per pixel, 0 when not multisampled.
+    pub fn samples (&self) -> u32 {
+        self.samples
+    }
+
+    pub fn width (&self) -> u32 {
+        self.width
+    }
+
+    pub fn height (&self) -> u32 {
+        self.height
+    }
+
+    pub fn size (&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+fn framebuffer_from_binding (binding: i32) -> Option<u32> {
+    if binding == 0 {
+        None
+    } else {
+        Some(binding as u32)
+    }
+}
+
+/// Reads a rectangle of the bound framebuffer as tightly packed RGBA. GL returns the bottom row
+/// first, the rows are flipped so the result is top row first.
+pub unsafe fn read_pixels (gl: &Context, x: i32, y: i32, width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = vec![0; (width * height * 4) as usize];
+    gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+    gl.read_pixels(x, y, width as i32, height as i32, glow::RGBA, glow::UNSIGNED_BYTE, PixelPackData::Slice(&mut pixels));
+
+    let row = (width * 4) as usize;
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for line in pixels.chunks_exact(row).rev() {
+        flipped.extend_from_slice(line);
+    }
+    flipped
+}
@@ -0,0 +1,394 @@
+use glow::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ab_glyph::{Font as _, FontArc, GlyphId, PxScale, PxScaleFont, ScaleFont};
+
+use crate::mesh::{float_bytes, index_bytes, point_attributes, VertexArray};
+use crate::{BlendMode, ColorUniforms, GLObject, Program, ProjectionUniforms, Texture, Transform2D, TransformUniforms, Uniforms, ATTRIBUTE_POSITION, ATTRIBUTE_TEX_COORDS};
+
+const ATTRIBUTES: &[(u32, i32)] = &[(ATTRIBUTE_POSITION, 2), (ATTRIBUTE_TEX_COORDS, 2)];
+// u16 indices, drawn in runs of this many quads
+const GLYPHS_PER_DRAW: usize = u16::MAX as usize / 4;
+const GLYPH_PADDING: u32 = 1;
+
+/// A TTF/OTF font.
+#[derive(Clone)]
+pub struct Font {
+    font: FontArc
+}
+
+impl Font {
+    pub fn from_bytes (data: Vec<u8>) -> Result<Self, ab_glyph::InvalidFont> {
+        Ok(Self {
+            font: FontArc::try_from_vec(data)?
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AtlasGlyph {
+    // offset of the bitmap's top left corner from the pen position on the baseline
+    offset: [f32; 2],
+    size: [f32; 2],
+    // min and max corners in uv space
+    uv: [f32; 4]
+}
+
+/// Glyph bitmaps of one font packed into a texture. Glyphs are rasterized the first time a
+/// `Text` needs them at a given size; once the texture is full, new glyphs are left out.
+pub struct GlyphAtlas {
+    font: Font,
+    texture: Arc<Texture>,
+    glyphs: HashMap<(GlyphId, u32), Option<AtlasGlyph>>,
+    // shelf packing: glyphs fill rows left to right, a row is as tall as its tallest glyph
+    cursor: (u32, u32),
+    row_height: u32,
+    full: bool
+}
+
+impl GlyphAtlas {
+    pub unsafe fn new (gl: Arc<Context>, font: Font, width: u32, height: u32) -> Self {
+        let texture = Texture::from_rgba(gl, width, height, Some(&vec![0; (width * height * 4) as usize]));
+
+        Self {
+            font,
+            texture: Arc::new(texture),
+            glyphs: HashMap::new(),
+            cursor: (GLYPH_PADDING, GLYPH_PADDING),
+            row_height: 0,
+            full: false
+        }
+    }
+
+    pub fn font (&self) -> &Font {
+        &self.font
+    }
+
+    pub fn texture (&self) -> &Arc<Texture> {
+        &self.texture
+    }
+
+    pub fn is_full (&self) -> bool {
+        self.full
+    }
+
+    /// Forgets every glyph, `Text`s laid out before have to be updated afterwards.
+    pub unsafe fn clear (&mut self) {
+        let (width, height) = self.texture.size();
+        self.texture.update(0, 0, width, height, &vec![0; (width * height * 4) as usize]);
+        self.glyphs.clear();
+        self.cursor = (GLYPH_PADDING, GLYPH_PADDING);
+        self.row_height = 0;
+        self.full = false;
+    }
+
+    unsafe fn glyph (&mut self, id: GlyphId, size: f32) -> Option<AtlasGlyph> {
+        let key = (id, size.to_bits());
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+
+        let glyph = self.rasterize(id, size);
+        // a glyph that didn't fit is retried after `clear`, blank glyphs like spaces are remembered
+        if glyph.is_some() || !self.full {
+            self.glyphs.insert(key, glyph);
+        }
+        glyph
+    }
+
+    unsafe fn rasterize (&mut self, id: GlyphId, size: f32) -> Option<AtlasGlyph> {
+        let outline = self.font.font.outline_glyph(id.with_scale(PxScale::from(size)))?;
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width().ceil() as u32, bounds.height().ceil() as u32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let (atlas_width, atlas_height) = self.texture.size();
+        if self.cursor.0 + width + GLYPH_PADDING > atlas_width {
+            self.cursor = (GLYPH_PADDING, self.cursor.1 + self.row_height + GLYPH_PADDING);
+            self.row_height = 0;
+        }
+        if self.cursor.0 + width + GLYPH_PADDING > atlas_width || self.cursor.1 + height + GLYPH_PADDING > atlas_height {
+            self.full = true;
+            return None;
+        }
+
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        outline.draw(|x, y, coverage| {
+            if x < width && y < height {
+                let value = (coverage.min(1.) * 255.) as u8;
+                let index = ((y * width + x) * 4) as usize;
+                pixels[index..index + 4].copy_from_slice(&[value; 4]);
+            }
+        });
+
+        let (x, y) = self.cursor;
+        self.texture.update(x, y, width, height, &pixels);
+        self.cursor.0 += width + GLYPH_PADDING;
+        self.row_height = self.row_height.max(height);
+
+        Some(AtlasGlyph {
+            offset: [bounds.min.x, bounds.min.y],
+            size: [width as f32, height as f32],
+            uv: [
+                x as f32 / atlas_width as f32,
+                y as f32 / atlas_height as f32,
+                (x + width) as f32 / atlas_width as f32,
+                (y + height) as f32 / atlas_height as f32
+            ]
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLayout {
+    pub size: f32,
+    pub align: TextAlign,
+    // lines are wrapped at word boundaries to fit, words wider than this overflow
+    pub max_width: Option<f32>,
+    pub line_spacing: f32
+}
+
+impl TextLayout {
+    pub fn new (size: f32) -> Self {
+        Self {
+            size,
+            align: TextAlign::Left,
+            max_width: None,
+            line_spacing: 1.
+        }
+    }
+
+    pub fn with_align (mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_max_width (mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_line_spacing (mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+}
+
+/// A laid out string, one textured quad per glyph. The origin of the transform passed to `draw_with`
+/// is the top left corner of the text block, in the same pixel coordinates as the other shapes.
+pub struct Text {
+    vertex_array: VertexArray,
+    glyphs: usize,
+    texture: Arc<Texture>,
+    content: String,
+    layout: TextLayout,
+    width: f32,
+    height: f32,
+    pub blend_mode: BlendMode,
+    gl: Arc<Context>
+}
+
+impl Text {
+    pub unsafe fn new (gl: Arc<Context>, atlas: &mut GlyphAtlas, content: &str, layout: TextLayout) -> Self {
+        let mut text = Self {
+            vertex_array: VertexArray::new(&gl, ATTRIBUTES),
+            glyphs: 0,
+            texture: atlas.texture().clone(),
+            content: String::new(),
+            layout,
+            width: 0.,
+            height: 0.,
+            blend_mode: BlendMode::Normal,
+            gl
+        };
+        text.update(atlas, content, layout);
+        text
+    }
+
+    pub unsafe fn update (&mut self, atlas: &mut GlyphAtlas, content: &str, layout: TextLayout) {
+        let mut vertices = Vec::new();
+        let mut glyphs = 0;
+        let (width, height) = layout_glyphs(atlas, content, &layout, |glyph, x, y| {
+            glyphs += 1;
+            let (left, top) = (x + glyph.offset[0], y + glyph.offset[1]);
+            let (right, bottom) = (left + glyph.size[0], top + glyph.size[1]);
+            let [u0, v0, u1, v1] = glyph.uv;
+            vertices.extend_from_slice(&[
+                left, top, u0, v0,
+                right, top, u1, v0,
+                right, bottom, u1, v1,
+                left, bottom, u0, v1
+            ]);
+        });
+
+        // every run of quads uses the same indices, the vertex attributes are moved along instead
+        let mut indices = Vec::with_capacity(glyphs.min(GLYPHS_PER_DRAW) * 6);
+        for glyph in 0..glyphs.min(GLYPHS_PER_DRAW) as u16 {
+            let base = glyph * 4;
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        self.vertex_array.bind(&self.gl);
+        self.gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &float_bytes(vertices), glow::STATIC_DRAW);
+        self.gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, &index_bytes(&indices), glow::STATIC_DRAW);
+
+        self.glyphs = glyphs;
+        self.texture = atlas.texture().clone();
+        self.content = content.to_string();
+        self.layout = layout;
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn content (&self) -> &str {
+        &self.content
+    }
+
+    pub fn layout (&self) -> &TextLayout {
+        &self.layout
+    }
+
+    /// Size of the laid out text block, changed by `update`.
+    pub fn width (&self) -> f32 {
+        self.width
+    }
+
+    pub fn height (&self) -> f32 {
+        self.height
+    }
+
+    pub fn draw_with(&self, program: &Program, transform: impl Into<Transform2D>, color: impl Into<ColorUniforms>, projection: impl Into<ProjectionUniforms>) {
+        let mut uniforms: Vec<Box<dyn Uniforms>> = Vec::new();
+        uniforms.push(Box::new(projection.into()));
+        uniforms.push(Box::new(TransformUniforms::from(transform.into())));
+        uniforms.push(Box::new(color.into()));
+        unsafe { self.render(program, uniforms) }
+    }
+}
+
+impl GLObject for Text {
+    unsafe fn render(&self, program: &Program, uniforms: Vec<Box<dyn Uniforms>>) {
+        program.bind();
+        self.blend_mode.apply(&self.gl);
+        self.texture.bind(0);
+        program.set_i32("atlas", 0);
+        self.vertex_array.bind(&self.gl);
+        for uniform in uniforms {
+            uniform.set_uniforms(program); // set up all the uniforms for our shader
+        }
+
+        if self.glyphs <= GLYPHS_PER_DRAW {
+            self.gl.draw_elements(TRIANGLES, self.glyphs as i32 * 6, UNSIGNED_SHORT, 0);
+            return;
+        }
+        for first_glyph in (0..self.glyphs).step_by(GLYPHS_PER_DRAW) {
+            point_attributes(&self.gl, ATTRIBUTES, first_glyph * 4);
+            let glyphs = (self.glyphs - first_glyph).min(GLYPHS_PER_DRAW);
+            self.gl.draw_elements(TRIANGLES, glyphs as i32 * 6, UNSIGNED_SHORT, 0);
+        }
+        point_attributes(&self.gl, ATTRIBUTES, 0);
+    }
+}
+
+/// Measures `content` without touching the GPU, returns the width and height of the text block.
+pub fn measure_text (font: &Font, content: &str, layout: &TextLayout) -> (f32, f32) {
+    let lines = wrap_lines(font, content, layout);
+    let scaled = font.font.as_scaled(PxScale::from(layout.size));
+    let width = lines.iter().map(|(_, width)| *width).fold(0., f32::max);
+    (width, lines.len() as f32 * line_height(&scaled, layout))
+}
+
+fn line_height (scaled: &PxScaleFont<&FontArc>, layout: &TextLayout) -> f32 {
+    (scaled.ascent() - scaled.descent() + scaled.line_gap()) * layout.line_spacing
+}
+
+fn advance_of (scaled: &PxScaleFont<&FontArc>, text: &str, mut previous: Option<GlyphId>) -> (f32, Option<GlyphId>) {
+    let mut width = 0.;
+    for character in text.chars() {
+        let id = scaled.glyph_id(character);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    (width, previous)
+}
+
+// greedy word wrap, returns every line with its width
+fn wrap_lines (font: &Font, content: &str, layout: &TextLayout) -> Vec<(String, f32)> {
+    let scaled = font.font.as_scaled(PxScale::from(layout.size));
+    let mut lines = Vec::new();
+
+    for paragraph in content.split('\n') {
+        let max_width = match layout.max_width {
+            Some(max_width) => max_width,
+            None => {
+                lines.push((paragraph.to_string(), advance_of(&scaled, paragraph, None).0));
+                continue;
+            }
+        };
+
+        let mut line = String::new();
+        let mut line_width = 0.;
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            let candidate_width = advance_of(&scaled, &candidate, None).0;
+            if candidate_width > max_width && !line.is_empty() {
+                lines.push((std::mem::take(&mut line), line_width));
+                line_width = advance_of(&scaled, word, None).0;
+                line = word.to_string();
+            } else {
+                line = candidate;
+                line_width = candidate_width;
+            }
+        }
+        lines.push((line, line_width));
+    }
+
+    lines
+}
+
+unsafe fn layout_glyphs<F: FnMut(&AtlasGlyph, f32, f32)> (atlas: &mut GlyphAtlas, content: &str, layout: &TextLayout, mut emit: F) -> (f32, f32) {
+    let font = atlas.font.clone();
+    let lines = wrap_lines(&font, content, layout);
+    let scaled = font.font.as_scaled(PxScale::from(layout.size));
+    let block_width = layout.max_width.unwrap_or_else(|| lines.iter().map(|(_, width)| *width).fold(0., f32::max));
+    let line_height = line_height(&scaled, layout);
+
+    let mut widest: f32 = 0.;
+    for (index, (line, width)) in lines.iter().enumerate() {
+        let mut x = match layout.align {
+            TextAlign::Left => 0.,
+            TextAlign::Center => (block_width - width) / 2.,
+            TextAlign::Right => block_width - width
+        };
+        let baseline = scaled.ascent() + index as f32 * line_height;
+        widest = widest.max(*width);
+
+        let mut previous = None;
+        for character in line.chars() {
+            let id = scaled.glyph_id(character);
+            if let Some(previous) = previous {
+                x += scaled.kern(previous, id);
+            }
+            if let Some(glyph) = atlas.glyph(id, layout.size) {
+                emit(&glyph, x, baseline);
+            }
+            x += scaled.h_advance(id);
+            previous = Some(id);
+        }
+    }
+
+    (widest, lines.len() as f32 * line_height)
+}
@@ -0,0 +1,147 @@
+use glow::*;
+use std::sync::Arc;
+
+use crate::resources::{GLTexture, Handle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    Nearest,
+    Linear
+}
+
+impl FilterMode {
+    fn gl_value (&self) -> i32 {
+        match self {
+            FilterMode::Nearest => glow::NEAREST as i32,
+            FilterMode::Linear => glow::LINEAR as i32
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat
+}
+
+impl WrapMode {
+    fn gl_value (&self) -> i32 {
+        match self {
+            WrapMode::ClampToEdge => glow::CLAMP_TO_EDGE as i32,
+            WrapMode::Repeat => glow::REPEAT as i32,
+            WrapMode::MirroredRepeat => glow::MIRRORED_REPEAT as i32
+        }
+    }
+}
+
+/// An RGBA8 2D texture. Rows are uploaded top to bottom, so v = 0 is the top of the image,
+/// which matches the y-down pixel coordinates of `ProjectionUniforms`.
+#[derive(Debug)]
+pub struct Texture {
+    texture: Handle<GLTexture>,
+    width: u32,
+    height: u32,
+    gl: Arc<Context>
+}
+
+impl Texture {
+    /// `data` is tightly packed RGBA, `width * height * 4` bytes, or `None` to leave the texture uninitialized.
+    pub unsafe fn from_rgba (gl: Arc<Context>, width: u32, height: u32, data: Option<&[u8]>) -> Self {
+        if let Some(data) = data {
+            assert_eq!(data.len(), (width * height * 4) as usize, "texture data doesn't match its size");
+        }
+
+        let texture = Handle::new(&gl).unwrap();
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture.raw()));
+        gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+        // unsized, ES 2.0 and WebGL1 want the internal format to match the format. Everything else picks RGBA8 for it
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            glow::RGBA as i32,
+            width as i32,
+            height as i32,
+            0,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            data
+        );
+
+        let texture = Self {
+            texture,
+            width,
+            height,
+            gl
+        };
+        texture.set_filter(FilterMode::Linear, FilterMode::Linear);
+        texture.set_wrap(WrapMode::ClampToEdge, WrapMode::ClampToEdge);
+        texture
+    }
+
+    /// Decodes a PNG or JPEG file held in memory.
+    #[cfg(feature = "image")]
+    pub unsafe fn from_image_bytes (gl: Arc<Context>, bytes: &[u8]) -> Result<Self, image::ImageError> {
+        let image = image::load_from_memory(bytes)?.into_rgba8();
+        Ok(Self::from_rgba(gl, image.width(), image.height(), Some(image.as_raw())))
+    }
+
+    #[cfg(feature = "image")]
+    pub unsafe fn from_file<P: AsRef<std::path::Path>> (gl: Arc<Context>, path: P) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.into_rgba8();
+        Ok(Self::from_rgba(gl, image.width(), image.height(), Some(image.as_raw())))
+    }
+
+    pub unsafe fn set_filter (&self, min: FilterMode, mag: FilterMode) {
+        self.gl.bind_texture(glow::TEXTURE_2D, Some(self.texture.raw()));
+        self.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, min.gl_value());
+        self.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, mag.gl_value());
+    }
+
+    pub unsafe fn set_wrap (&self, s: WrapMode, t: WrapMode) {
+        self.gl.bind_texture(glow::TEXTURE_2D, Some(self.texture.raw()));
+        self.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, s.gl_value());
+        self.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, t.gl_value());
+    }
+
+    /// Replaces a sub-rectangle of the texture with tightly packed RGBA data.
+    pub unsafe fn update (&self, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        assert_eq!(data.len(), (width * height * 4) as usize, "texture data doesn't match its size");
+        assert!(x + width <= self.width && y + height <= self.height, "update is out of the texture's bounds");
+
+        self.gl.bind_texture(glow::TEXTURE_2D, Some(self.texture.raw()));
+        self.gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+        self.gl.tex_sub_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            x as i32,
+            y as i32,
+            width as i32,
+            height as i32,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            PixelUnpackData::Slice(data)
+        );
+    }
+
+    pub unsafe fn bind (&self, unit: u32) {
+        self.gl.active_texture(glow::TEXTURE0 + unit);
+        self.gl.bind_texture(glow::TEXTURE_2D, Some(self.texture.raw()));
+    }
+
+    pub fn raw (&self) -> u32 {
+        self.texture.raw()
+    }
+
+    pub fn width (&self) -> u32 {
+        self.width
+    }
+
+    pub fn height (&self) -> u32 {
+        self.height
+    }
+
+    pub fn size (&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
@@ -0,0 +1,163 @@
+use std::ops::Mul;
+
+use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector2, Vector3};
+
+use crate::TransformUniforms;
+
+/// A 2D affine transform. The builder methods apply in local space like `TransformUniforms`,
+/// so `Transform2D::translation(x, y).rotate(angle)` places a shape at (x, y) then spins it
+/// around that point. `a * b` applies `b` first, then `a`. A bare `Vector2` converts to a
+/// translation, so draw calls that take a transform also accept a position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform2D {
+    matrix: Matrix3<f32>
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform2D {
+    pub fn identity () -> Self {
+        Self {
+            matrix: Matrix3::identity()
+        }
+    }
+
+    pub fn from_matrix (matrix: Matrix3<f32>) -> Self {
+        Self {
+            matrix
+        }
+    }
+
+    pub fn translation (x: f32, y: f32) -> Self {
+        Self::from_matrix(Matrix3::from_translation(Vector2::new(x, y)))
+    }
+
+    pub fn rotation (angle: f32) -> Self {
+        Self::from_matrix(Matrix3::from_angle_z(cgmath::Rad(angle)))
+    }
+
+    pub fn scaling (x: f32, y: f32) -> Self {
+        Self::from_matrix(Matrix3::from_nonuniform_scale(x, y))
+    }
+
+    /// Shears by `x` radians along the x axis and `y` radians along the y axis.
+    pub fn skewing (x: f32, y: f32) -> Self {
+        Self::from_matrix(Matrix3::new(
+            1., y.tan(), 0.,
+            x.tan(), 1., 0.,
+            0., 0., 1.
+        ))
+    }
+
+    pub fn translate (self, x: f32, y: f32) -> Self {
+        self * Self::translation(x, y)
+    }
+
+    pub fn rotate (self, angle: f32) -> Self {
+        self * Self::rotation(angle)
+    }
+
+    /// Rotates around `pivot`, in the local coordinates of the shape.
+    pub fn rotate_around (self, angle: f32, pivot: Vector2<f32>) -> Self {
+        self.translate(pivot.x, pivot.y).rotate(angle).translate(-pivot.x, -pivot.y)
+    }
+
+    pub fn scale (self, x: f32, y: f32) -> Self {
+        self * Self::scaling(x, y)
+    }
+
+    /// Scales around `pivot`, in the local coordinates of the shape.
+    pub fn scale_around (self, x: f32, y: f32, pivot: Vector2<f32>) -> Self {
+        self.translate(pivot.x, pivot.y).scale(x, y).translate(-pivot.x, -pivot.y)
+    }
+
+    pub fn skew (self, x: f32, y: f32) -> Self {
+        self * Self::skewing(x, y)
+    }
+
+    /// `other` applied after `self`, the same as `other * self`.
+    pub fn then (self, other: Transform2D) -> Self {
+        other * self
+    }
+
+    /// `None` when the transform collapses the plane, e.g. a scale of zero.
+    pub fn inverse (&self) -> Option<Self> {
+        self.matrix.invert().map(Self::from_matrix)
+    }
+
+    pub fn transform_point (&self, point: Vector2<f32>) -> Vector2<f32> {
+        let point = self.matrix * Vector3::new(point.x, point.y, 1.);
+        Vector2::new(point.x, point.y)
+    }
+
+    pub fn transform_vector (&self, vector: Vector2<f32>) -> Vector2<f32> {
+        let vector = self.matrix * Vector3::new(vector.x, vector.y, 0.);
+        Vector2::new(vector.x, vector.y)
+    }
+
+    pub fn matrix (&self) -> Matrix3<f32> {
+        self.matrix
+    }
+
+    pub fn to_matrix4 (&self) -> Matrix4<f32> {
+        let m = self.matrix;
+        Matrix4::new(
+            m.x.x, m.x.y, 0., 0.,
+            m.y.x, m.y.y, 0., 0.,
+            0., 0., 1., 0.,
+            m.z.x, m.z.y, 0., 1.
+        )
+    }
+}
+
+impl Mul for Transform2D {
+    type Output = Transform2D;
+
+    fn mul(self, other: Transform2D) -> Transform2D {
+        Self::from_matrix(self.matrix * other.matrix)
+    }
+}
+
+impl From<Vector2<f32>> for Transform2D {
+    fn from(position: Vector2<f32>) -> Self {
+        Self::translation(position.x, position.y)
+    }
+}
+
+impl From<Transform2D> for TransformUniforms {
+    fn from(transform: Transform2D) -> Self {
+        Self {
+            transform: transform.to_matrix4()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    fn assert_close (actual: Vector2<f32>, expected: Vector2<f32>) {
+        assert!((actual - expected).magnitude() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn then_applies_other_last () {
+        let quarter = std::f32::consts::FRAC_PI_2;
+        let transform = Transform2D::translation(10., 0.).then(Transform2D::rotation(quarter));
+        // translated to (11, 0), then turned to (0, 11)
+        assert_close(transform.transform_point(Vector2::new(1., 0.)), Vector2::new(0., 11.));
+        assert_eq!(transform, Transform2D::rotation(quarter) * Transform2D::translation(10., 0.));
+    }
+
+    #[test]
+    fn builders_apply_in_local_space () {
+        let transform = Transform2D::translation(10., 0.).rotate(std::f32::consts::FRAC_PI_2);
+        // turned to (0, 1) around the origin, then translated
+        assert_close(transform.transform_point(Vector2::new(1., 0.)), Vector2::new(10., 1.));
+    }
+}
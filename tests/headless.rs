@@ -0,0 +1,92 @@
+#![cfg(feature = "headless")]
+
+use glow::HasContext;
+use vsb::headless::{compare_with_png, save_png, HeadlessContext};
+use vsb::*;
+
+const GOLDEN_CIRCLE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/circle.png");
+
+unsafe fn draw_circle (context: &HeadlessContext) {
+    let gl = context.gl();
+    let shaders = ShaderLibrary::new(gl).unwrap();
+    set_clear_color(gl, ColorUniforms::new(0., 0., 0.));
+    gl.clear(glow::COLOR_BUFFER_BIT);
+    let circle = Circle::new(gl.clone(), 20.).unwrap();
+    circle.draw_with(&shaders.solid, cgmath::vec2(32., 32.), Color::from_hex("#ff8000").unwrap(), context.size());
+}
+
+// set VSB_UPDATE_GOLDEN=1 to rewrite the reference image instead of comparing against it
+#[test]
+fn circle_matches_golden_image () {
+    unsafe {
+        let context = HeadlessContext::new(64, 64).unwrap();
+        draw_circle(&context);
+        let pixels = context.read_pixels();
+        if std::env::var_os("VSB_UPDATE_GOLDEN").is_some() {
+            save_png(GOLDEN_CIRCLE, &pixels, 64, 64).unwrap();
+        }
+        let comparison = compare_with_png(&pixels, 64, 64, GOLDEN_CIRCLE, 2).unwrap();
+        assert!(comparison.is_match(), "{:?}", comparison);
+    }
+}
+
+#[test]
+fn reads_its_own_target () {
+    unsafe {
+        let context = HeadlessContext::new(64, 64).unwrap();
+        draw_circle(&context);
+        let other = RenderTarget::new(context.gl().clone(), 64, 64, false).unwrap();
+        other.bind();
+        let comparison = compare_with_png(&context.read_pixels(), 64, 64, GOLDEN_CIRCLE, 2).unwrap();
+        assert!(comparison.is_match(), "{:?}", comparison);
+    }
+}
+
+#[test]
+fn rejects_invalid_tolerances () {
+    unsafe {
+        let context = HeadlessContext::new(64, 64).unwrap();
+        let gl = context.gl();
+        for tolerance in [0., -1., f32::NAN, f32::INFINITY].iter() {
+            assert!(Circle::with_tolerance(gl.clone(), 10., *tolerance).is_err());
+            assert!(Circle::with_tolerance(gl.clone(), 10., Tolerance::Auto { pixels: *tolerance }).is_err());
+        }
+
+        let mut circle = Circle::new(gl.clone(), 10.).unwrap();
+        assert!(circle.set_tolerance(0.).is_err());
+        assert_eq!(circle.mesh().tolerance(), Tolerance::default());
+        assert!(circle.set_tolerance(MIN_TOLERANCE).is_ok());
+    }
+}
+
+#[test]
+fn reports_shapes_that_outlive_the_context () {
+    unsafe {
+        let context = HeadlessContext::new(64, 64).unwrap();
+        let circle = Circle::new(context.gl().clone(), 10.).unwrap();
+        let leaks = context.close();
+        assert_eq!(leaks.count(ResourceKind::VertexArray), 1, "{}", leaks);
+        assert_eq!(leaks.count(ResourceKind::Buffer), 2, "{}", leaks);
+        // after the report, dropping it is ignored
+        drop(circle);
+    }
+}
+
+#[test]
+fn collects_dropped_handles () {
+    unsafe {
+        let context = HeadlessContext::new(64, 64).unwrap();
+        let gl = context.gl();
+        let registry = ResourceRegistry::of(gl);
+        let live = registry.live();
+
+        let circle = Circle::new(gl.clone(), 10.).unwrap();
+        assert_eq!(registry.live(), live + 3);
+        drop(circle);
+        assert_eq!(registry.pending(), 3);
+        assert_eq!(registry.collect(gl), 3);
+        assert_eq!(registry.pending(), 0);
+        assert_eq!(registry.live(), live);
+        assert!(context.close().is_empty());
+    }
+}